@@ -1,5 +1,6 @@
 use std::rc::Rc;
-use crate::lexer::position::{Located, Position};
+use super::compiler::CompileError;
+use crate::lexer::position::{Located, Span};
 
 pub type Register = u16;
 pub type Address = u32;
@@ -26,6 +27,16 @@ pub enum ByteCode {
     Return {
         src: Option<Register>,
     },
+    /// installs an error handler at `addr` that receives the error in `dst`
+    Try {
+        addr: Address,
+        dst: Register,
+    },
+    /// removes the innermost error handler of the current call
+    EndTry,
+    Throw {
+        src: Register,
+    },
 
     Move {
         dst: Register,
@@ -39,10 +50,67 @@ pub enum ByteCode {
         dst: Register,
         addr: Address,
     },
+    Boolean {
+        dst: Register,
+        value: bool,
+    },
+    Null {
+        dst: Register,
+    },
     Closure {
         dst: Register,
         addr: Address,
     },
+    GetUpvalue {
+        dst: Register,
+        addr: Address,
+    },
+    SetUpvalue {
+        addr: Address,
+        src: Register,
+    },
+    Close {
+        start: Register,
+    },
+    List {
+        dst: Register,
+        offset: Register,
+        len: Register,
+    },
+    Map {
+        dst: Register,
+    },
+
+    IterNew {
+        dst: Register,
+        src: Register,
+    },
+    IterNext {
+        dst: Register,
+        iter: Register,
+        addr: Address,
+    },
+
+    Index {
+        dst: Register,
+        head: Register,
+        index: Register,
+    },
+    SetIndex {
+        head: Register,
+        index: Register,
+        src: Register,
+    },
+    Field {
+        dst: Register,
+        head: Register,
+        addr: Address,
+    },
+    SetField {
+        head: Register,
+        addr: Address,
+        src: Register,
+    },
 
     Global {
         dst: Register,
@@ -88,14 +156,30 @@ pub enum BinaryOperation {
 pub enum UnaryOperation {
     Neg,
     Not,
+    ToString,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Upvalue {
+    Local(Register),
+    Upvalue(Address),
 }
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct Closure {
+    pub name: String,
+    pub path: String,
+    /// the number of declared parameters, not counting a variadic one
+    pub params: u8,
+    /// whether arguments past `params` are collected into a list
+    pub variadic: bool,
+    /// entry points for calls that leave out default parameters: `defaults[i]` is where a call
+    /// giving `i` of them starts, the last entry is the start of the body
+    pub defaults: Vec<Address>,
     pub code: Vec<Located<ByteCode>>,
     pub registers: Register,
     pub strings: Vec<String>,
     pub numbers: Vec<f64>,
     pub closures: Vec<Rc<Self>>,
+    pub upvalues: Vec<Upvalue>,
 }
 
 impl Closure {
@@ -108,28 +192,51 @@ impl Closure {
     pub fn closure(&self, addr: Address) -> Option<&Rc<Closure>> {
         self.closures.get(addr as usize)
     }
-    pub fn new_string(&mut self, string: String) -> Address {
+    pub fn upvalue(&self, addr: Address) -> Option<&Upvalue> {
+        self.upvalues.get(addr as usize)
+    }
+    /// the address execution starts at when called with `args` arguments
+    pub fn entry(&self, args: usize) -> Address {
+        let Some(body) = self.defaults.last() else {
+            return 0;
+        };
+        let required = self.params as usize + 1 - self.defaults.len();
+        *self.defaults.get(args.saturating_sub(required)).unwrap_or(body)
+    }
+    pub fn new_string(&mut self, string: String) -> Result<Address, CompileError> {
         if let Some(addr) = self.strings.iter().position(|s| s == &string) {
-            return addr as Address
+            return Ok(addr as Address)
         }
-        let addr = self.strings.len();
+        let addr = Address::try_from(self.strings.len())
+            .map_err(|_| CompileError::TooManyConstants)?;
         self.strings.push(string);
-        addr as Address
+        Ok(addr)
     }
-    pub fn new_closure(&mut self, closure: Rc<Closure>) -> Address {
-        let addr = self.closures.len();
+    pub fn new_closure(&mut self, closure: Rc<Closure>) -> Result<Address, CompileError> {
+        let addr = Address::try_from(self.closures.len())
+            .map_err(|_| CompileError::TooManyConstants)?;
         self.closures.push(closure);
-        addr as Address
+        Ok(addr)
     }
-    pub fn new_number(&mut self, number: f64) -> Address {
+    pub fn new_upvalue(&mut self, upvalue: Upvalue) -> Result<Address, CompileError> {
+        if let Some(addr) = self.upvalues.iter().position(|u| u == &upvalue) {
+            return Ok(addr as Address)
+        }
+        let addr = Address::try_from(self.upvalues.len())
+            .map_err(|_| CompileError::TooManyConstants)?;
+        self.upvalues.push(upvalue);
+        Ok(addr)
+    }
+    pub fn new_number(&mut self, number: f64) -> Result<Address, CompileError> {
         if let Some(addr) = self.numbers.iter().position(|n| n == &number) {
-            return addr as Address
+            return Ok(addr as Address)
         }
-        let addr = self.numbers.len();
+        let addr = Address::try_from(self.numbers.len())
+            .map_err(|_| CompileError::TooManyConstants)?;
         self.numbers.push(number);
-        addr as Address
+        Ok(addr)
     }
-    pub fn write(&mut self, bytecode: ByteCode, pos: Position) -> Address {
+    pub fn write(&mut self, bytecode: ByteCode, pos: Span) -> Address {
         let addr = self.code.len();
         self.code.push(Located::new(bytecode, pos));
         addr as Address
@@ -1,21 +1,47 @@
-use super::bytecode::{Address, BinaryOperation, ByteCode, Closure, Register, UnaryOperation};
-use crate::{lexer::position::Located, parser::ast::*};
-use std::{collections::HashMap, rc::Rc};
+use super::bytecode::{
+    Address, BinaryOperation, ByteCode, Closure, Register, UnaryOperation, Upvalue,
+};
+use crate::{
+    diagnostic::diagnostic::Diagnose,
+    lexer::position::{Located, Span},
+    parser::ast::*,
+};
+use std::{collections::HashMap, fmt::Display, rc::Rc};
 
 #[derive(Debug, Default)]
 pub struct Compiler {
     pub frames: Vec<Frame>,
+    pub strict: bool,
+    pub top_level_globals: bool,
+    pub path: String,
 }
 #[derive(Debug, Clone, PartialEq)]
 pub struct Frame {
     pub closure: Closure,
     pub registers: Register,
     pub scopes: Vec<Scope>,
+    pub loops: Vec<Loop>,
+    /// the number of `try` bodies the current code is nested in
+    pub tries: usize,
 }
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct Scope {
     pub locals: HashMap<String, Register>,
     pub offset: Register,
+    pub captured: bool,
+}
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Loop {
+    pub offset: Register,
+    pub tries: usize,
+    pub breaks: Vec<Address>,
+    pub continues: Vec<Address>,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variable {
+    Local(Register),
+    Upvalue(Address),
+    Global,
 }
 
 pub trait Compilable {
@@ -26,9 +52,14 @@ pub trait Compilable {
 impl Compiler {
     pub fn push_frame(&mut self) {
         self.frames.push(Frame {
-            closure: Closure::default(),
+            closure: Closure {
+                path: self.path.clone(),
+                ..Default::default()
+            },
             registers: 0,
             scopes: vec![Scope::default()],
+            loops: vec![],
+            tries: 0,
         })
     }
     pub fn pop_frame(&mut self) -> Option<Frame> {
@@ -40,6 +71,121 @@ impl Compiler {
     pub fn frame_mut(&mut self) -> &mut Frame {
         self.frames.last_mut().unwrap()
     }
+    pub fn resolve(&mut self, ident: &str) -> Result<Variable, CompileError> {
+        if let Some(reg) = self.frame().local(ident) {
+            return Ok(Variable::Local(reg));
+        }
+        if let Some(addr) = self.upvalue(self.frames.len() - 1, ident)? {
+            return Ok(Variable::Upvalue(addr));
+        }
+        Ok(Variable::Global)
+    }
+    fn upvalue(&mut self, depth: usize, ident: &str) -> Result<Option<Address>, CompileError> {
+        if depth == 0 {
+            return Ok(None);
+        }
+        let upvalue = if let Some(reg) = self.frames[depth - 1].capture(ident) {
+            Upvalue::Local(reg)
+        } else if let Some(addr) = self.upvalue(depth - 1, ident)? {
+            Upvalue::Upvalue(addr)
+        } else {
+            return Ok(None);
+        };
+        self.frames[depth].closure.new_upvalue(upvalue).map(Some)
+    }
+    pub fn get(&mut self, ident: String, pos: Span) -> Result<Register, Located<CompileError>> {
+        let variable = self
+            .resolve(&ident)
+            .map_err(|err| Located::new(err, pos.clone()))?;
+        match variable {
+            Variable::Local(reg) => Ok(reg),
+            Variable::Upvalue(addr) => {
+                let dst = self
+                    .frame_mut()
+                    .new_register()
+                    .map_err(|err| Located::new(err, pos.clone()))?;
+                self.frame_mut()
+                    .closure
+                    .write(ByteCode::GetUpvalue { dst, addr }, pos);
+                Ok(dst)
+            }
+            Variable::Global => {
+                let addr = self
+                    .frame_mut()
+                    .closure
+                    .new_string(ident)
+                    .map_err(|err| Located::new(err, pos.clone()))?;
+                let dst = self
+                    .frame_mut()
+                    .new_register()
+                    .map_err(|err| Located::new(err, pos.clone()))?;
+                self.frame_mut()
+                    .closure
+                    .write(ByteCode::Global { dst, addr }, pos);
+                Ok(dst)
+            }
+        }
+    }
+    pub fn set(
+        &mut self,
+        ident: String,
+        src: Register,
+        pos: Span,
+    ) -> Result<(), Located<CompileError>> {
+        let variable = self
+            .resolve(&ident)
+            .map_err(|err| Located::new(err, pos.clone()))?;
+        match variable {
+            Variable::Local(dst) => {
+                self.frame_mut()
+                    .closure
+                    .write(ByteCode::Move { dst, src }, pos);
+            }
+            Variable::Upvalue(addr) => {
+                self.frame_mut()
+                    .closure
+                    .write(ByteCode::SetUpvalue { addr, src }, pos);
+            }
+            Variable::Global => {
+                if self.strict {
+                    return Err(Located::new(CompileError::UndeclaredVariable(ident), pos));
+                }
+                self.set_global(ident, src, pos)?;
+            }
+        }
+        Ok(())
+    }
+    pub fn set_global(
+        &mut self,
+        ident: String,
+        src: Register,
+        pos: Span,
+    ) -> Result<(), Located<CompileError>> {
+        let addr = self
+            .frame_mut()
+            .closure
+            .new_string(ident)
+            .map_err(|err| Located::new(err, pos.clone()))?;
+        self.frame_mut()
+            .closure
+            .write(ByteCode::SetGlobal { addr, src }, pos);
+        Ok(())
+    }
+    pub fn new_parameter(
+        &mut self,
+        Located { value: ident, pos }: Located<String>,
+    ) -> Result<Register, Located<CompileError>> {
+        if self.frame().local(&ident).is_some() {
+            return Err(Located::new(CompileError::DuplicateParameter(ident), pos));
+        }
+        self.frame_mut()
+            .new_local(ident)
+            .map_err(|err| Located::new(err, pos))
+    }
+    /// top-level `let` and `def` define globals when `top_level_globals` is set (REPL)
+    pub fn declares_global(&self) -> bool {
+        self.top_level_globals && self.frames.len() == 1 && self.frame().scopes.len() == 1
+    }
 }
 impl Frame {
     pub fn push_scope(&mut self) {
@@ -48,10 +194,10 @@ impl Frame {
             ..Default::default()
         });
     }
-    pub fn pop_scope(&mut self) {
-        if let Some(scope) = self.scopes.pop() {
-            self.registers = scope.offset;
-        }
+    pub fn pop_scope(&mut self) -> Option<Scope> {
+        let scope = self.scopes.pop()?;
+        self.registers = scope.offset;
+        Some(scope)
     }
     pub fn local(&self, ident: &str) -> Option<Register> {
         self.scopes
@@ -59,39 +205,191 @@ impl Frame {
             .rev()
             .find_map(|scope| scope.locals.get(ident).copied())
     }
-    pub fn new_register(&mut self) -> Register {
+    pub fn capture(&mut self, ident: &str) -> Option<Register> {
+        let scope = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find(|scope| scope.locals.contains_key(ident))?;
+        scope.captured = true;
+        scope.locals.get(ident).copied()
+    }
+    pub fn new_register(&mut self) -> Result<Register, CompileError> {
+        self.new_registers(1)
+    }
+    pub fn push_loop(&mut self) {
+        self.loops.push(Loop {
+            offset: self.registers,
+            tries: self.tries,
+            ..Default::default()
+        });
+    }
+    pub fn pop_loop(&mut self, continue_addr: Address, break_addr: Address) {
+        let Loop {
+            offset: _,
+            tries: _,
+            breaks,
+            continues,
+        } = self.loops.pop().expect("no loop to pop");
+        for addr in breaks {
+            self.closure
+                .overwrite(addr, ByteCode::Jump { addr: break_addr });
+        }
+        for addr in continues {
+            self.closure.overwrite(
+                addr,
+                ByteCode::Jump {
+                    addr: continue_addr,
+                },
+            );
+        }
+    }
+    /// removes the handlers of the `tries` innermost `try` bodies when jumping out of them
+    pub fn leave_tries(&mut self, tries: usize, pos: &Span) {
+        for _ in 0..tries {
+            self.closure.write(ByteCode::EndTry, pos.clone());
+        }
+    }
+    pub fn hoist(&mut self, stats: &[Located<Statement>]) -> Result<(), Located<CompileError>> {
+        for stat in stats {
+            if let Statement::Def { ident, .. } = &stat.value {
+                self.new_local(ident.value.clone())
+                    .map_err(|err| Located::new(err, ident.pos.clone()))?;
+            }
+        }
+        Ok(())
+    }
+    pub fn new_registers(&mut self, len: Register) -> Result<Register, CompileError> {
         let register = self.registers;
-        self.registers += 1;
+        self.registers = self
+            .registers
+            .checked_add(len)
+            .ok_or(CompileError::TooManyRegisters)?;
         if self.closure.registers < self.registers {
             self.closure.registers = self.registers;
         }
-        register
+        Ok(register)
     }
-    pub fn new_local(&mut self, ident: String) -> Register {
-        let register = self.new_register();
+    pub fn new_local(&mut self, ident: String) -> Result<Register, CompileError> {
+        let register = self.new_register()?;
         self.scopes
             .last_mut()
             .unwrap()
             .locals
             .insert(ident, register);
-        register
+        Ok(register)
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum CompileError {}
+pub enum CompileError {
+    InvalidAssignTarget,
+    AssignToLiteral(&'static str),
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+    UndeclaredVariable(String),
+    DuplicateParameter(String),
+    ReturnOutsideFunction,
+    TooManyArguments(usize),
+    TooManyParameters(usize),
+    TooManyItems(usize),
+    TooManyRegisters,
+    TooManyConstants,
+}
+impl Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompileError::InvalidAssignTarget => write!(f, "invalid assignment target"),
+            CompileError::AssignToLiteral(literal) => write!(f, "cannot assign to {literal}"),
+            CompileError::BreakOutsideLoop => write!(f, "break outside of a loop"),
+            CompileError::ContinueOutsideLoop => write!(f, "continue outside of a loop"),
+            CompileError::UndeclaredVariable(ident) => {
+                write!(f, "assignment to undeclared variable {ident:?}")
+            }
+            CompileError::DuplicateParameter(ident) => {
+                write!(f, "duplicate parameter {ident:?}")
+            }
+            CompileError::ReturnOutsideFunction => write!(f, "return outside of a function"),
+            CompileError::TooManyArguments(len) => {
+                write!(f, "too many arguments ({len}), the maximum is {}", u8::MAX)
+            }
+            CompileError::TooManyParameters(len) => {
+                write!(f, "too many parameters ({len}), the maximum is {}", u8::MAX)
+            }
+            CompileError::TooManyItems(len) => {
+                write!(f, "too many list items ({len}), the maximum is {}", Register::MAX)
+            }
+            CompileError::TooManyRegisters => {
+                write!(f, "function needs more than {} registers", Register::MAX)
+            }
+            CompileError::TooManyConstants => {
+                write!(f, "function has more than {} constants", Address::MAX)
+            }
+        }
+    }
+}
+impl Diagnose for CompileError {
+    fn label(&self) -> Option<String> {
+        match self {
+            CompileError::InvalidAssignTarget => Some("cannot be assigned to".into()),
+            CompileError::AssignToLiteral(_) => Some("this is a literal".into()),
+            CompileError::UndeclaredVariable(_) => Some("not declared".into()),
+            CompileError::DuplicateParameter(_) => Some("already a parameter".into()),
+            _ => None,
+        }
+    }
+    fn notes(&self) -> Vec<String> {
+        match self {
+            CompileError::InvalidAssignTarget => {
+                vec!["only variables, indices and fields can be assigned to".into()]
+            }
+            CompileError::UndeclaredVariable(_) => {
+                vec!["strict mode does not create globals implicitly".into()]
+            }
+            _ => vec![],
+        }
+    }
+    fn help(&self) -> Option<String> {
+        match self {
+            CompileError::UndeclaredVariable(ident) => {
+                Some(format!("declare it first with `let {ident} = ...`"))
+            }
+            CompileError::TooManyArguments(_) => Some("pass the arguments as a list".into()),
+            _ => None,
+        }
+    }
+}
 impl Compilable for Located<Chunk> {
     type Output = Closure;
     type Error = Located<CompileError>;
     fn compile(self, compiler: &mut Compiler) -> Result<Self::Output, Self::Error> {
         compiler.push_frame();
-        for stat in self.value.0 {
+        compiler.frame_mut().closure.name = "<main>".into();
+        if !compiler.top_level_globals {
+            compiler.frame_mut().hoist(&self.value.0)?;
+        }
+        let mut stats = self.value.0;
+        let result = match stats.pop() {
+            Some(Located {
+                value: Statement::Expression(expr),
+                pos: _,
+            }) => Some(expr),
+            stat => {
+                stats.extend(stat);
+                None
+            }
+        };
+        for stat in stats {
             stat.compile(compiler)?;
         }
+        let src = match result {
+            Some(expr) => Some(expr.compile(compiler)?),
+            None => None,
+        };
         compiler
             .frame_mut()
             .closure
-            .write(ByteCode::Return { src: None }, self.pos);
+            .write(ByteCode::Return { src }, self.pos);
         let frame = compiler.pop_frame().unwrap();
         Ok(frame.closure)
     }
@@ -100,14 +398,24 @@ impl Compilable for Located<Block> {
     type Output = Option<u16>;
     type Error = Located<CompileError>;
     fn compile(self, compiler: &mut Compiler) -> Result<Self::Output, Self::Error> {
+        let Located { value: block, pos } = self;
         compiler.frame_mut().push_scope();
-        for stat in self.value.0 {
+        compiler.frame_mut().hoist(&block.0)?;
+        for stat in block.0 {
             if let Some(reg) = stat.compile(compiler)? {
                 compiler.frame_mut().pop_scope();
                 return Ok(Some(reg));
             }
         }
-        compiler.frame_mut().pop_scope();
+        let scope = compiler.frame_mut().pop_scope().unwrap();
+        if scope.captured {
+            compiler.frame_mut().closure.write(
+                ByteCode::Close {
+                    start: scope.offset,
+                },
+                pos,
+            );
+        }
         Ok(None)
     }
 }
@@ -126,7 +434,15 @@ impl Compilable for Located<Statement> {
                     },
                 expr,
             } => {
-                let reg = compiler.frame_mut().new_local(ident);
+                if compiler.declares_global() {
+                    let src = expr.compile(compiler)?;
+                    compiler.set_global(ident, src, pos)?;
+                    return Ok(None);
+                }
+                let reg = compiler
+                    .frame_mut()
+                    .new_local(ident)
+                    .map_err(|err| Located::new(err, pos.clone()))?;
                 let src = expr.compile(compiler)?;
                 compiler
                     .frame_mut()
@@ -135,68 +451,65 @@ impl Compilable for Located<Statement> {
                 Ok(None)
             }
             Statement::Assign {
-                ident:
+                target:
                     Located {
-                        value: ident,
-                        pos: _,
+                        value: target,
+                        pos: target_pos,
                     },
                 expr,
-            } => {
-                let reg = compiler.frame_mut().local(&ident);
-                let src = expr.compile(compiler)?;
-                if let Some(reg) = reg {
-                    compiler
-                        .frame_mut()
-                        .closure
-                        .write(ByteCode::Move { dst: reg, src }, pos);
-                } else {
-                    let addr = compiler.frame_mut().closure.new_string(ident);
+            } => match target {
+                Expression::Atom(Atom::Ident(ident)) => {
+                    let src = expr.compile(compiler)?;
+                    compiler.set(ident, src, target_pos)?;
+                    Ok(None)
+                }
+                Expression::Index { head, index } => {
+                    let head = head.compile(compiler)?;
+                    let index = index.compile(compiler)?;
+                    let src = expr.compile(compiler)?;
                     compiler
                         .frame_mut()
                         .closure
-                        .write(ByteCode::SetGlobal { addr, src }, pos);
+                        .write(ByteCode::SetIndex { head, index, src }, pos);
+                    Ok(None)
                 }
-                Ok(None)
-            }
-            Statement::Call {
-                ident:
-                    Located {
-                        value: ident,
-                        pos: ident_pos,
-                    },
-                args,
-            } => {
-                let func = if let Some(reg) = compiler.frame_mut().local(&ident) {
-                    reg
-                } else {
-                    let addr = compiler.frame_mut().closure.new_string(ident);
-                    let dst = compiler.frame_mut().new_register();
-                    compiler
+                Expression::Field {
+                    head,
+                    field:
+                        Located {
+                            value: field,
+                            pos: _,
+                        },
+                } => {
+                    let head = head.compile(compiler)?;
+                    let addr = compiler
                         .frame_mut()
                         .closure
-                        .write(ByteCode::Global { dst, addr }, ident_pos);
-                    dst
-                };
-                let args_len = args.len() as u8;
-                let offset = compiler.frame().registers;
-                compiler.frame_mut().registers += args_len as Register;
-                for (reg, arg) in (offset..offset + args_len as Register).zip(args) {
-                    let pos = arg.pos.clone();
-                    let src = arg.compile(compiler)?;
+                        .new_string(field)
+                        .map_err(|err| Located::new(err, pos.clone()))?;
+                    let src = expr.compile(compiler)?;
                     compiler
                         .frame_mut()
                         .closure
-                        .write(ByteCode::Move { dst: reg, src }, pos);
+                        .write(ByteCode::SetField { head, addr, src }, pos);
+                    Ok(None)
                 }
-                compiler.frame_mut().closure.write(
-                    ByteCode::Call {
-                        func,
-                        offset,
-                        args_len,
-                        dst: None,
-                    },
-                    pos,
-                );
+                Expression::Atom(Atom::Boolean(true)) => Err(Located::new(
+                    CompileError::AssignToLiteral("true"),
+                    target_pos,
+                )),
+                Expression::Atom(Atom::Boolean(false)) => Err(Located::new(
+                    CompileError::AssignToLiteral("false"),
+                    target_pos,
+                )),
+                Expression::Atom(Atom::Null) => Err(Located::new(
+                    CompileError::AssignToLiteral("null"),
+                    target_pos,
+                )),
+                _ => Err(Located::new(CompileError::InvalidAssignTarget, target_pos)),
+            },
+            Statement::Expression(expr) => {
+                expr.compile(compiler)?;
                 Ok(None)
             }
             Statement::Def {
@@ -206,17 +519,55 @@ impl Compilable for Located<Statement> {
                         pos: _,
                     },
                 params,
+                rest,
                 body,
+                doc: _,
             } => {
-                let reg = compiler.frame_mut().new_local(ident);
+                let name = ident.clone();
+                let global = compiler.declares_global().then(|| ident.clone());
+                let scope = compiler.frame().scopes.last().unwrap();
+                let reg = match scope.locals.get(&ident) {
+                    Some(reg) => *reg,
+                    None if global.is_some() => compiler
+                        .frame_mut()
+                        .new_register()
+                        .map_err(|err| Located::new(err, pos.clone()))?,
+                    None => compiler
+                        .frame_mut()
+                        .new_local(ident)
+                        .map_err(|err| Located::new(err, pos.clone()))?,
+                };
                 let addr = {
                     compiler.push_frame();
-                    for Located {
-                        value: param,
-                        pos: _,
-                    } in params
-                    {
-                        compiler.frame_mut().new_local(param);
+                    compiler.frame_mut().closure.name = name;
+                    compiler.frame_mut().closure.params = u8::try_from(params.len())
+                        .map_err(|_| {
+                            Located::new(CompileError::TooManyParameters(params.len()), pos.clone())
+                        })?;
+                    compiler.frame_mut().closure.variadic = rest.is_some();
+                    let mut defaults = vec![];
+                    for Parameter { ident, default } in params {
+                        let reg = compiler.new_parameter(ident)?;
+                        if let Some(default) = default {
+                            defaults.push((reg, default));
+                        }
+                    }
+                    if let Some(ident) = rest {
+                        compiler.new_parameter(ident)?;
+                    }
+                    if !defaults.is_empty() {
+                        for (dst, default) in defaults {
+                            let addr = compiler.frame().closure.code.len() as Address;
+                            compiler.frame_mut().closure.defaults.push(addr);
+                            let default_pos = default.pos.clone();
+                            let src = default.compile(compiler)?;
+                            compiler
+                                .frame_mut()
+                                .closure
+                                .write(ByteCode::Move { dst, src }, default_pos);
+                        }
+                        let addr = compiler.frame().closure.code.len() as Address;
+                        compiler.frame_mut().closure.defaults.push(addr);
                     }
                     body.compile(compiler)?;
                     compiler
@@ -225,12 +576,19 @@ impl Compilable for Located<Statement> {
                         .write(ByteCode::Return { src: None }, pos.clone());
                     let frame = compiler.pop_frame().unwrap();
                     let closure = Rc::new(frame.closure);
-                    compiler.frame_mut().closure.new_closure(closure)
+                    compiler
+                        .frame_mut()
+                        .closure
+                        .new_closure(closure)
+                        .map_err(|err| Located::new(err, pos.clone()))?
                 };
                 compiler
                     .frame_mut()
                     .closure
-                    .write(ByteCode::Closure { dst: reg, addr }, pos);
+                    .write(ByteCode::Closure { dst: reg, addr }, pos.clone());
+                if let Some(ident) = global {
+                    compiler.set_global(ident, reg, pos)?;
+                }
                 Ok(None)
             }
             Statement::If {
@@ -271,12 +629,14 @@ impl Compilable for Located<Statement> {
                     .frame_mut()
                     .closure
                     .write(ByteCode::default(), pos.clone());
+                compiler.frame_mut().push_loop();
                 body.compile(compiler)?;
                 compiler
                     .frame_mut()
                     .closure
                     .write(ByteCode::Jump { addr: cond_addr }, pos);
                 let exit_addr = compiler.frame_mut().closure.code.len() as Address;
+                compiler.frame_mut().pop_loop(cond_addr, exit_addr);
                 compiler.frame_mut().closure.overwrite(
                     check_addr,
                     ByteCode::JumpIf {
@@ -287,7 +647,71 @@ impl Compilable for Located<Statement> {
                 );
                 Ok(None)
             }
+            Statement::For {
+                ident:
+                    Located {
+                        value: ident,
+                        pos: _,
+                    },
+                iter,
+                body,
+            } => {
+                let iter_pos = iter.pos.clone();
+                let src = iter.compile(compiler)?;
+                compiler.frame_mut().push_scope();
+                let iter = compiler
+                    .frame_mut()
+                    .new_register()
+                    .map_err(|err| Located::new(err, pos.clone()))?;
+                compiler
+                    .frame_mut()
+                    .closure
+                    .write(ByteCode::IterNew { dst: iter, src }, iter_pos);
+                compiler.frame_mut().push_loop();
+                let dst = compiler
+                    .frame_mut()
+                    .new_local(ident)
+                    .map_err(|err| Located::new(err, pos.clone()))?;
+                let next_addr = compiler
+                    .frame_mut()
+                    .closure
+                    .write(ByteCode::default(), pos.clone());
+                body.compile(compiler)?;
+                if compiler.frame().scopes.last().unwrap().captured {
+                    compiler
+                        .frame_mut()
+                        .closure
+                        .write(ByteCode::Close { start: dst }, pos.clone());
+                }
+                compiler
+                    .frame_mut()
+                    .closure
+                    .write(ByteCode::Jump { addr: next_addr }, pos.clone());
+                let exit_addr = compiler.frame_mut().closure.code.len() as Address;
+                compiler.frame_mut().closure.overwrite(
+                    next_addr,
+                    ByteCode::IterNext {
+                        dst,
+                        iter,
+                        addr: exit_addr,
+                    },
+                );
+                compiler.frame_mut().pop_loop(next_addr, exit_addr);
+                let scope = compiler.frame_mut().pop_scope().unwrap();
+                if scope.captured {
+                    compiler.frame_mut().closure.write(
+                        ByteCode::Close {
+                            start: scope.offset,
+                        },
+                        pos,
+                    );
+                }
+                Ok(None)
+            }
             Statement::Return(expr) => {
+                if compiler.frames.len() == 1 {
+                    return Err(Located::new(CompileError::ReturnOutsideFunction, pos));
+                }
                 let src = expr.compile(compiler)?;
                 compiler
                     .frame_mut()
@@ -295,6 +719,92 @@ impl Compilable for Located<Statement> {
                     .write(ByteCode::Return { src: Some(src) }, pos);
                 Ok(Some(src))
             }
+            Statement::Try {
+                body,
+                ident:
+                    Located {
+                        value: ident,
+                        pos: ident_pos,
+                    },
+                handler,
+            } => {
+                let try_addr = compiler
+                    .frame_mut()
+                    .closure
+                    .write(ByteCode::default(), pos.clone());
+                compiler.frame_mut().tries += 1;
+                body.compile(compiler)?;
+                compiler.frame_mut().tries -= 1;
+                compiler
+                    .frame_mut()
+                    .closure
+                    .write(ByteCode::EndTry, pos.clone());
+                let exit_addr = compiler
+                    .frame_mut()
+                    .closure
+                    .write(ByteCode::default(), pos.clone());
+                let handler_addr = compiler.frame().closure.code.len() as Address;
+                compiler.frame_mut().push_scope();
+                let dst = compiler
+                    .frame_mut()
+                    .new_local(ident)
+                    .map_err(|err| Located::new(err, ident_pos))?;
+                handler.compile(compiler)?;
+                let scope = compiler.frame_mut().pop_scope().unwrap();
+                if scope.captured {
+                    compiler.frame_mut().closure.write(
+                        ByteCode::Close {
+                            start: scope.offset,
+                        },
+                        pos.clone(),
+                    );
+                }
+                let end_addr = compiler.frame().closure.code.len() as Address;
+                compiler.frame_mut().closure.overwrite(
+                    try_addr,
+                    ByteCode::Try {
+                        addr: handler_addr,
+                        dst,
+                    },
+                );
+                compiler
+                    .frame_mut()
+                    .closure
+                    .overwrite(exit_addr, ByteCode::Jump { addr: end_addr });
+                Ok(None)
+            }
+            Statement::Throw(expr) => {
+                let src = expr.compile(compiler)?;
+                compiler
+                    .frame_mut()
+                    .closure
+                    .write(ByteCode::Throw { src }, pos);
+                Ok(None)
+            }
+            Statement::Break => {
+                let frame = compiler.frame_mut();
+                let Some(Loop { offset, tries, .. }) = frame.loops.last() else {
+                    return Err(Located::new(CompileError::BreakOutsideLoop, pos));
+                };
+                let (start, tries) = (*offset, frame.tries - tries);
+                frame.leave_tries(tries, &pos);
+                frame.closure.write(ByteCode::Close { start }, pos.clone());
+                let addr = frame.closure.write(ByteCode::default(), pos);
+                frame.loops.last_mut().unwrap().breaks.push(addr);
+                Ok(None)
+            }
+            Statement::Continue => {
+                let frame = compiler.frame_mut();
+                let Some(Loop { offset, tries, .. }) = frame.loops.last() else {
+                    return Err(Located::new(CompileError::ContinueOutsideLoop, pos));
+                };
+                let (start, tries) = (*offset, frame.tries - tries);
+                frame.leave_tries(tries, &pos);
+                frame.closure.write(ByteCode::Close { start }, pos.clone());
+                let addr = frame.closure.write(ByteCode::default(), pos);
+                frame.loops.last_mut().unwrap().continues.push(addr);
+                Ok(None)
+            }
         }
     }
 }
@@ -323,6 +833,7 @@ impl From<UnaryOperator> for UnaryOperation {
         match value {
             UnaryOperator::Minus => Self::Neg,
             UnaryOperator::Exclamation => Self::Not,
+            UnaryOperator::ToString => Self::ToString,
         }
     }
 }
@@ -333,8 +844,45 @@ impl Compilable for Located<Expression> {
         let Located { value: expr, pos } = self;
         match expr {
             Expression::Atom(atom) => Located::new(atom, pos).compile(compiler),
+            Expression::Binary {
+                op: op @ (BinaryOperator::Ampersand | BinaryOperator::Pipe),
+                left,
+                right,
+            } => {
+                let dst = compiler
+                    .frame_mut()
+                    .new_register()
+                    .map_err(|err| Located::new(err, pos.clone()))?;
+                let src = left.compile(compiler)?;
+                compiler
+                    .frame_mut()
+                    .closure
+                    .write(ByteCode::Move { dst, src }, pos.clone());
+                let check_addr = compiler
+                    .frame_mut()
+                    .closure
+                    .write(ByteCode::default(), pos.clone());
+                let src = right.compile(compiler)?;
+                compiler
+                    .frame_mut()
+                    .closure
+                    .write(ByteCode::Move { dst, src }, pos);
+                let exit_addr = compiler.frame_mut().closure.code.len() as Address;
+                compiler.frame_mut().closure.overwrite(
+                    check_addr,
+                    ByteCode::JumpIf {
+                        not: op == BinaryOperator::Ampersand,
+                        cond: dst,
+                        addr: exit_addr,
+                    },
+                );
+                Ok(dst)
+            }
             Expression::Binary { op, left, right } => {
-                let dst = compiler.frame_mut().new_register();
+                let dst = compiler
+                    .frame_mut()
+                    .new_register()
+                    .map_err(|err| Located::new(err, pos.clone()))?;
                 let left = left.compile(compiler)?;
                 let right = right.compile(compiler)?;
                 let op = op.into();
@@ -350,7 +898,10 @@ impl Compilable for Located<Expression> {
                 Ok(dst)
             }
             Expression::Unary { op, right } => {
-                let dst = compiler.frame_mut().new_register();
+                let dst = compiler
+                    .frame_mut()
+                    .new_register()
+                    .map_err(|err| Located::new(err, pos.clone()))?;
                 let src = right.compile(compiler)?;
                 let op = op.into();
                 compiler
@@ -360,11 +911,18 @@ impl Compilable for Located<Expression> {
                 Ok(dst)
             }
             Expression::Call { head, args } => {
-                let dst = compiler.frame_mut().new_register();
+                let dst = compiler
+                    .frame_mut()
+                    .new_register()
+                    .map_err(|err| Located::new(err, pos.clone()))?;
                 let func = head.compile(compiler)?;
-                let args_len = args.len() as u8;
-                let offset = compiler.frame().registers;
-                compiler.frame_mut().registers += args_len as Register;
+                let args_len = u8::try_from(args.len()).map_err(|_| {
+                    Located::new(CompileError::TooManyArguments(args.len()), pos.clone())
+                })?;
+                let offset = compiler
+                    .frame_mut()
+                    .new_registers(args_len as Register)
+                    .map_err(|err| Located::new(err, pos.clone()))?;
                 for (reg, arg) in (offset..offset + args_len as Register).zip(args) {
                     let pos = arg.pos.clone();
                     let src = arg.compile(compiler)?;
@@ -384,6 +942,43 @@ impl Compilable for Located<Expression> {
                 );
                 Ok(dst)
             }
+            Expression::Index { head, index } => {
+                let dst = compiler
+                    .frame_mut()
+                    .new_register()
+                    .map_err(|err| Located::new(err, pos.clone()))?;
+                let head = head.compile(compiler)?;
+                let index = index.compile(compiler)?;
+                compiler
+                    .frame_mut()
+                    .closure
+                    .write(ByteCode::Index { dst, head, index }, pos);
+                Ok(dst)
+            }
+            Expression::Field {
+                head,
+                field:
+                    Located {
+                        value: field,
+                        pos: _,
+                    },
+            } => {
+                let dst = compiler
+                    .frame_mut()
+                    .new_register()
+                    .map_err(|err| Located::new(err, pos.clone()))?;
+                let head = head.compile(compiler)?;
+                let addr = compiler
+                    .frame_mut()
+                    .closure
+                    .new_string(field)
+                    .map_err(|err| Located::new(err, pos.clone()))?;
+                compiler
+                    .frame_mut()
+                    .closure
+                    .write(ByteCode::Field { dst, head, addr }, pos);
+                Ok(dst)
+            }
         }
     }
 }
@@ -394,20 +989,17 @@ impl Compilable for Located<Atom> {
         let Located { value: atom, pos } = self;
         match atom {
             Atom::Expression(expr) => expr.compile(compiler),
-            Atom::Ident(ident) => Ok(if let Some(reg) = compiler.frame_mut().local(&ident) {
-                reg
-            } else {
-                let addr = compiler.frame_mut().closure.new_string(ident);
-                let dst = compiler.frame_mut().new_register();
-                compiler
+            Atom::Ident(ident) => compiler.get(ident, pos),
+            Atom::Number(number) => {
+                let addr = compiler
                     .frame_mut()
                     .closure
-                    .write(ByteCode::Global { dst, addr }, pos);
-                dst
-            }),
-            Atom::Number(number) => {
-                let addr = compiler.frame_mut().closure.new_number(number);
-                let dst = compiler.frame_mut().new_register();
+                    .new_number(number)
+                    .map_err(|err| Located::new(err, pos.clone()))?;
+                let dst = compiler
+                    .frame_mut()
+                    .new_register()
+                    .map_err(|err| Located::new(err, pos.clone()))?;
                 compiler
                     .frame_mut()
                     .closure
@@ -415,14 +1007,97 @@ impl Compilable for Located<Atom> {
                 Ok(dst)
             }
             Atom::String(string) => {
-                let addr = compiler.frame_mut().closure.new_string(string);
-                let dst = compiler.frame_mut().new_register();
+                let addr = compiler
+                    .frame_mut()
+                    .closure
+                    .new_string(string)
+                    .map_err(|err| Located::new(err, pos.clone()))?;
+                let dst = compiler
+                    .frame_mut()
+                    .new_register()
+                    .map_err(|err| Located::new(err, pos.clone()))?;
                 compiler
                     .frame_mut()
                     .closure
                     .write(ByteCode::String { dst, addr }, pos);
                 Ok(dst)
             }
+            Atom::Boolean(value) => {
+                let dst = compiler
+                    .frame_mut()
+                    .new_register()
+                    .map_err(|err| Located::new(err, pos.clone()))?;
+                compiler
+                    .frame_mut()
+                    .closure
+                    .write(ByteCode::Boolean { dst, value }, pos);
+                Ok(dst)
+            }
+            Atom::Null => {
+                let dst = compiler
+                    .frame_mut()
+                    .new_register()
+                    .map_err(|err| Located::new(err, pos.clone()))?;
+                compiler
+                    .frame_mut()
+                    .closure
+                    .write(ByteCode::Null { dst }, pos);
+                Ok(dst)
+            }
+            Atom::List(items) => {
+                let dst = compiler
+                    .frame_mut()
+                    .new_register()
+                    .map_err(|err| Located::new(err, pos.clone()))?;
+                let len = Register::try_from(items.len()).map_err(|_| {
+                    Located::new(CompileError::TooManyItems(items.len()), pos.clone())
+                })?;
+                let offset = compiler
+                    .frame_mut()
+                    .new_registers(len)
+                    .map_err(|err| Located::new(err, pos.clone()))?;
+                for (reg, item) in (offset..offset + len).zip(items) {
+                    let pos = item.pos.clone();
+                    let src = item.compile(compiler)?;
+                    compiler
+                        .frame_mut()
+                        .closure
+                        .write(ByteCode::Move { dst: reg, src }, pos);
+                }
+                compiler
+                    .frame_mut()
+                    .closure
+                    .write(ByteCode::List { dst, offset, len }, pos);
+                Ok(dst)
+            }
+            Atom::Map(entries) => {
+                let dst = compiler
+                    .frame_mut()
+                    .new_register()
+                    .map_err(|err| Located::new(err, pos.clone()))?;
+                compiler
+                    .frame_mut()
+                    .closure
+                    .write(ByteCode::Map { dst }, pos);
+                for (Located { value: key, pos: _ }, expr) in entries {
+                    let pos = expr.pos.clone();
+                    let addr = compiler
+                        .frame_mut()
+                        .closure
+                        .new_string(key)
+                        .map_err(|err| Located::new(err, pos.clone()))?;
+                    let src = expr.compile(compiler)?;
+                    compiler.frame_mut().closure.write(
+                        ByteCode::SetField {
+                            head: dst,
+                            addr,
+                            src,
+                        },
+                        pos,
+                    );
+                }
+                Ok(dst)
+            }
         }
     }
 }
@@ -0,0 +1,77 @@
+use super::bytecode::{ByteCode, Closure};
+use crate::lexer::position::Located;
+use std::fmt::{self, Display};
+
+impl Closure {
+    fn disassemble(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "closure {} ({} params{}, {} registers, {} upvalues, {} instructions)",
+            self.name,
+            self.params,
+            if self.variadic { " + rest" } else { "" },
+            self.registers,
+            self.upvalues.len(),
+            self.code.len()
+        )?;
+        for (
+            addr,
+            Located {
+                value: bytecode,
+                pos,
+            },
+        ) in self.code.iter().enumerate()
+        {
+            let location = format!("{}:{}", pos.start.ln + 1, pos.start.col + 1);
+            let mut line = format!("  {addr:04}  {location:<8}{bytecode:?}");
+            if let Some(comment) = self.comment(bytecode) {
+                line = format!("{line:<60} ; {comment}");
+            }
+            writeln!(f, "{line}")?;
+        }
+        if let Some((body, defaults)) = self.defaults.split_last() {
+            for (index, addr) in defaults.iter().enumerate() {
+                writeln!(f, "  default {index}: {addr:04}")?;
+            }
+            writeln!(f, "  body: {body:04}")?;
+        }
+        for (addr, upvalue) in self.upvalues.iter().enumerate() {
+            writeln!(f, "  upvalue {addr}: {upvalue:?}")?;
+        }
+        for closure in &self.closures {
+            writeln!(f)?;
+            closure.disassemble(f)?;
+        }
+        Ok(())
+    }
+    fn comment(&self, bytecode: &ByteCode) -> Option<String> {
+        match *bytecode {
+            ByteCode::String { dst: _, addr }
+            | ByteCode::Global { dst: _, addr }
+            | ByteCode::SetGlobal { addr, src: _ }
+            | ByteCode::Field {
+                dst: _,
+                head: _,
+                addr,
+            }
+            | ByteCode::SetField {
+                head: _,
+                addr,
+                src: _,
+            } => self.string(addr).map(|string| format!("{string:?}")),
+            ByteCode::Number { dst: _, addr } => self.number(addr).map(|number| number.to_string()),
+            ByteCode::Closure { dst: _, addr } => self
+                .closure(addr)
+                .map(|closure| format!("closure {}", closure.name)),
+            ByteCode::GetUpvalue { dst: _, addr } | ByteCode::SetUpvalue { addr, src: _ } => {
+                self.upvalue(addr).map(|upvalue| format!("{upvalue:?}"))
+            }
+            _ => None,
+        }
+    }
+}
+impl Display for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.disassemble(f)
+    }
+}
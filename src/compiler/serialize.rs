@@ -0,0 +1,726 @@
+use super::bytecode::{
+    Address, BinaryOperation, ByteCode, Closure, Register, UnaryOperation, Upvalue,
+};
+use crate::lexer::position::{Located, Position, Span};
+use std::{fmt::Display, rc::Rc};
+
+pub const MAGIC: &[u8; 4] = b"\0cbc";
+pub const VERSION: u16 = 1;
+/// how deep closures may be nested, so a crafted file can't overflow the stack while loading
+pub const MAX_NESTING: usize = 256;
+/// operations in discriminant order, so that `op as u8` indexes them
+const BINARY: [BinaryOperation; 14] = [
+    BinaryOperation::Add,
+    BinaryOperation::Sub,
+    BinaryOperation::Mul,
+    BinaryOperation::Div,
+    BinaryOperation::Mod,
+    BinaryOperation::Pow,
+    BinaryOperation::EQ,
+    BinaryOperation::NE,
+    BinaryOperation::LT,
+    BinaryOperation::GT,
+    BinaryOperation::LE,
+    BinaryOperation::GE,
+    BinaryOperation::And,
+    BinaryOperation::Or,
+];
+const UNARY: [UnaryOperation; 3] = [
+    UnaryOperation::Neg,
+    UnaryOperation::Not,
+    UnaryOperation::ToString,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadError {
+    BadMagic,
+    UnsupportedVersion(u16),
+    UnexpectedEnd,
+    TrailingBytes,
+    InvalidOpcode(u8),
+    InvalidOperation(u8),
+    InvalidUpvalue(u8),
+    InvalidString,
+    InvalidRegister { addr: Address, register: Register },
+    InvalidAddress { addr: Address, target: Address },
+    InvalidCapture,
+    InvalidParameters,
+    MissingReturn,
+    TooDeeplyNested,
+}
+impl Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::BadMagic => write!(f, "not a bytecode file"),
+            LoadError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported bytecode version {version}, expected {VERSION}"
+            ),
+            LoadError::UnexpectedEnd => write!(f, "unexpected end of bytecode"),
+            LoadError::TrailingBytes => write!(f, "trailing bytes after bytecode"),
+            LoadError::InvalidOpcode(opcode) => write!(f, "invalid opcode {opcode}"),
+            LoadError::InvalidOperation(op) => write!(f, "invalid operation {op}"),
+            LoadError::InvalidUpvalue(kind) => write!(f, "invalid upvalue kind {kind}"),
+            LoadError::InvalidString => write!(f, "string constant is not valid utf-8"),
+            LoadError::InvalidRegister { addr, register } => {
+                write!(
+                    f,
+                    "instruction {addr} uses out of range register {register}"
+                )
+            }
+            LoadError::InvalidAddress { addr, target } => {
+                write!(
+                    f,
+                    "instruction {addr} refers to out of range address {target}"
+                )
+            }
+            LoadError::InvalidCapture => write!(f, "upvalue captures an invalid variable"),
+            LoadError::InvalidParameters => write!(f, "invalid parameter layout"),
+            LoadError::MissingReturn => write!(f, "code does not end in a return or jump"),
+            LoadError::TooDeeplyNested => {
+                write!(f, "closures are nested more than {MAX_NESTING} levels deep")
+            }
+        }
+    }
+}
+
+pub fn serialize(closure: &Closure) -> Vec<u8> {
+    let mut writer = Writer::default();
+    writer.bytes.extend_from_slice(MAGIC);
+    writer.u16(VERSION);
+    writer.closure(closure);
+    writer.bytes
+}
+pub fn deserialize(bytes: &[u8]) -> Result<Closure, LoadError> {
+    let mut reader = Reader {
+        bytes,
+        index: 0,
+        depth: 0,
+    };
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(LoadError::BadMagic);
+    }
+    let version = reader.u16()?;
+    if version != VERSION {
+        return Err(LoadError::UnsupportedVersion(version));
+    }
+    let closure = reader.closure(None)?;
+    if reader.index != bytes.len() {
+        return Err(LoadError::TrailingBytes);
+    }
+    Ok(closure)
+}
+
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+    fn u16(&mut self, value: u16) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    fn len(&mut self, len: usize) {
+        self.u32(len as u32);
+    }
+    fn option(&mut self, register: Option<Register>) {
+        match register {
+            Some(register) => {
+                self.u8(1);
+                self.u16(register);
+            }
+            None => self.u8(0),
+        }
+    }
+    fn position(&mut self, pos: &Position) {
+        self.len(pos.ln);
+        self.len(pos.col);
+        self.len(pos.offset);
+    }
+    fn string(&mut self, string: &str) {
+        self.len(string.len());
+        self.bytes.extend_from_slice(string.as_bytes());
+    }
+    fn closure(&mut self, closure: &Closure) {
+        self.string(&closure.name);
+        self.string(&closure.path);
+        self.u16(closure.registers);
+        self.u8(closure.params);
+        self.u8(closure.variadic as u8);
+        self.len(closure.defaults.len());
+        for addr in &closure.defaults {
+            self.u32(*addr);
+        }
+        self.len(closure.strings.len());
+        for string in &closure.strings {
+            self.string(string);
+        }
+        self.len(closure.numbers.len());
+        for number in &closure.numbers {
+            self.bytes.extend_from_slice(&number.to_le_bytes());
+        }
+        self.len(closure.upvalues.len());
+        for upvalue in &closure.upvalues {
+            match *upvalue {
+                Upvalue::Local(register) => {
+                    self.u8(0);
+                    self.u32(register as u32);
+                }
+                Upvalue::Upvalue(addr) => {
+                    self.u8(1);
+                    self.u32(addr);
+                }
+            }
+        }
+        self.len(closure.closures.len());
+        for closure in &closure.closures {
+            self.closure(closure);
+        }
+        self.len(closure.code.len());
+        for Located {
+            value: bytecode,
+            pos,
+        } in &closure.code
+        {
+            self.position(&pos.start);
+            self.position(&pos.end);
+            self.bytecode(bytecode);
+        }
+    }
+    fn bytecode(&mut self, bytecode: &ByteCode) {
+        match *bytecode {
+            ByteCode::None => self.u8(0),
+            ByteCode::Jump { addr } => {
+                self.u8(1);
+                self.u32(addr);
+            }
+            ByteCode::JumpIf { not, cond, addr } => {
+                self.u8(2);
+                self.u8(not as u8);
+                self.u16(cond);
+                self.u32(addr);
+            }
+            ByteCode::Call {
+                func,
+                offset,
+                args_len,
+                dst,
+            } => {
+                self.u8(3);
+                self.u16(func);
+                self.u16(offset);
+                self.u8(args_len);
+                self.option(dst);
+            }
+            ByteCode::Return { src } => {
+                self.u8(4);
+                self.option(src);
+            }
+            ByteCode::Move { dst, src } => {
+                self.u8(5);
+                self.u16(dst);
+                self.u16(src);
+            }
+            ByteCode::String { dst, addr } => {
+                self.u8(6);
+                self.u16(dst);
+                self.u32(addr);
+            }
+            ByteCode::Number { dst, addr } => {
+                self.u8(7);
+                self.u16(dst);
+                self.u32(addr);
+            }
+            ByteCode::Boolean { dst, value } => {
+                self.u8(8);
+                self.u16(dst);
+                self.u8(value as u8);
+            }
+            ByteCode::Null { dst } => {
+                self.u8(9);
+                self.u16(dst);
+            }
+            ByteCode::Closure { dst, addr } => {
+                self.u8(10);
+                self.u16(dst);
+                self.u32(addr);
+            }
+            ByteCode::GetUpvalue { dst, addr } => {
+                self.u8(11);
+                self.u16(dst);
+                self.u32(addr);
+            }
+            ByteCode::SetUpvalue { addr, src } => {
+                self.u8(12);
+                self.u32(addr);
+                self.u16(src);
+            }
+            ByteCode::Close { start } => {
+                self.u8(13);
+                self.u16(start);
+            }
+            ByteCode::List { dst, offset, len } => {
+                self.u8(14);
+                self.u16(dst);
+                self.u16(offset);
+                self.u16(len);
+            }
+            ByteCode::Map { dst } => {
+                self.u8(15);
+                self.u16(dst);
+            }
+            ByteCode::IterNew { dst, src } => {
+                self.u8(16);
+                self.u16(dst);
+                self.u16(src);
+            }
+            ByteCode::IterNext { dst, iter, addr } => {
+                self.u8(17);
+                self.u16(dst);
+                self.u16(iter);
+                self.u32(addr);
+            }
+            ByteCode::Index { dst, head, index } => {
+                self.u8(18);
+                self.u16(dst);
+                self.u16(head);
+                self.u16(index);
+            }
+            ByteCode::SetIndex { head, index, src } => {
+                self.u8(19);
+                self.u16(head);
+                self.u16(index);
+                self.u16(src);
+            }
+            ByteCode::Field { dst, head, addr } => {
+                self.u8(20);
+                self.u16(dst);
+                self.u16(head);
+                self.u32(addr);
+            }
+            ByteCode::SetField { head, addr, src } => {
+                self.u8(21);
+                self.u16(head);
+                self.u32(addr);
+                self.u16(src);
+            }
+            ByteCode::Global { dst, addr } => {
+                self.u8(22);
+                self.u16(dst);
+                self.u32(addr);
+            }
+            ByteCode::SetGlobal { addr, src } => {
+                self.u8(23);
+                self.u32(addr);
+                self.u16(src);
+            }
+            ByteCode::Binary {
+                op,
+                dst,
+                left,
+                right,
+            } => {
+                self.u8(24);
+                self.u8(op as u8);
+                self.u16(dst);
+                self.u16(left);
+                self.u16(right);
+            }
+            ByteCode::Unary { op, dst, src } => {
+                self.u8(25);
+                self.u8(op as u8);
+                self.u16(dst);
+                self.u16(src);
+            }
+            ByteCode::Try { addr, dst } => {
+                self.u8(26);
+                self.u32(addr);
+                self.u16(dst);
+            }
+            ByteCode::EndTry => self.u8(27),
+            ByteCode::Throw { src } => {
+                self.u8(28);
+                self.u16(src);
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    index: usize,
+    /// how many closures enclose the one being read
+    depth: usize,
+}
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], LoadError> {
+        let bytes = self
+            .bytes
+            .get(self.index..self.index + len)
+            .ok_or(LoadError::UnexpectedEnd)?;
+        self.index += len;
+        Ok(bytes)
+    }
+    fn u8(&mut self) -> Result<u8, LoadError> {
+        Ok(self.take(1)?[0])
+    }
+    fn bool(&mut self) -> Result<bool, LoadError> {
+        Ok(self.u8()? != 0)
+    }
+    fn u16(&mut self) -> Result<u16, LoadError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+    fn u32(&mut self) -> Result<u32, LoadError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    fn len(&mut self) -> Result<usize, LoadError> {
+        Ok(self.u32()? as usize)
+    }
+    fn string(&mut self) -> Result<String, LoadError> {
+        let len = self.len()?;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| LoadError::InvalidString)
+    }
+    fn position(&mut self) -> Result<Position, LoadError> {
+        Ok(Position::new(self.len()?, self.len()?, self.len()?))
+    }
+    fn option(&mut self) -> Result<Option<Register>, LoadError> {
+        Ok(if self.bool()? {
+            Some(self.u16()?)
+        } else {
+            None
+        })
+    }
+    /// reads a closure, `parent` holds the register count and upvalues of the enclosing closure
+    fn closure(&mut self, parent: Option<(Register, usize)>) -> Result<Closure, LoadError> {
+        let name = self.string()?;
+        let path = self.string()?;
+        let registers = self.u16()?;
+        let params = self.u8()?;
+        let variadic = self.bool()?;
+        let mut defaults = vec![];
+        for _ in 0..self.len()? {
+            defaults.push(self.u32()?);
+        }
+        let mut strings = vec![];
+        for _ in 0..self.len()? {
+            strings.push(self.string()?);
+        }
+        let mut numbers = vec![];
+        for _ in 0..self.len()? {
+            numbers.push(f64::from_le_bytes(self.take(8)?.try_into().unwrap()));
+        }
+        let mut upvalues = vec![];
+        for _ in 0..self.len()? {
+            let kind = self.u8()?;
+            let index = self.u32()?;
+            let upvalue = match kind {
+                0 => Upvalue::Local(
+                    Register::try_from(index).map_err(|_| LoadError::InvalidCapture)?,
+                ),
+                1 => Upvalue::Upvalue(index),
+                kind => return Err(LoadError::InvalidUpvalue(kind)),
+            };
+            let valid = match (upvalue, parent) {
+                (Upvalue::Local(register), Some((registers, _))) => register < registers,
+                (Upvalue::Upvalue(addr), Some((_, upvalues))) => (addr as usize) < upvalues,
+                (_, None) => false,
+            };
+            if !valid {
+                return Err(LoadError::InvalidCapture);
+            }
+            upvalues.push(upvalue);
+        }
+        let mut closures = vec![];
+        for _ in 0..self.len()? {
+            if self.depth == MAX_NESTING {
+                return Err(LoadError::TooDeeplyNested);
+            }
+            self.depth += 1;
+            let closure = self.closure(Some((registers, upvalues.len())));
+            self.depth -= 1;
+            closures.push(Rc::new(closure?));
+        }
+        let mut code = vec![];
+        for _ in 0..self.len()? {
+            let pos = Span::new(self.position()?, self.position()?);
+            code.push(Located::new(self.bytecode()?, pos));
+        }
+        let closure = Closure {
+            name,
+            path,
+            params,
+            variadic,
+            defaults,
+            code,
+            registers,
+            strings,
+            numbers,
+            closures,
+            upvalues,
+        };
+        verify(&closure)?;
+        Ok(closure)
+    }
+    fn bytecode(&mut self) -> Result<ByteCode, LoadError> {
+        Ok(match self.u8()? {
+            0 => ByteCode::None,
+            1 => ByteCode::Jump { addr: self.u32()? },
+            2 => ByteCode::JumpIf {
+                not: self.bool()?,
+                cond: self.u16()?,
+                addr: self.u32()?,
+            },
+            3 => ByteCode::Call {
+                func: self.u16()?,
+                offset: self.u16()?,
+                args_len: self.u8()?,
+                dst: self.option()?,
+            },
+            4 => ByteCode::Return {
+                src: self.option()?,
+            },
+            5 => ByteCode::Move {
+                dst: self.u16()?,
+                src: self.u16()?,
+            },
+            6 => ByteCode::String {
+                dst: self.u16()?,
+                addr: self.u32()?,
+            },
+            7 => ByteCode::Number {
+                dst: self.u16()?,
+                addr: self.u32()?,
+            },
+            8 => ByteCode::Boolean {
+                dst: self.u16()?,
+                value: self.bool()?,
+            },
+            9 => ByteCode::Null { dst: self.u16()? },
+            10 => ByteCode::Closure {
+                dst: self.u16()?,
+                addr: self.u32()?,
+            },
+            11 => ByteCode::GetUpvalue {
+                dst: self.u16()?,
+                addr: self.u32()?,
+            },
+            12 => ByteCode::SetUpvalue {
+                addr: self.u32()?,
+                src: self.u16()?,
+            },
+            13 => ByteCode::Close { start: self.u16()? },
+            14 => ByteCode::List {
+                dst: self.u16()?,
+                offset: self.u16()?,
+                len: self.u16()?,
+            },
+            15 => ByteCode::Map { dst: self.u16()? },
+            16 => ByteCode::IterNew {
+                dst: self.u16()?,
+                src: self.u16()?,
+            },
+            17 => ByteCode::IterNext {
+                dst: self.u16()?,
+                iter: self.u16()?,
+                addr: self.u32()?,
+            },
+            18 => ByteCode::Index {
+                dst: self.u16()?,
+                head: self.u16()?,
+                index: self.u16()?,
+            },
+            19 => ByteCode::SetIndex {
+                head: self.u16()?,
+                index: self.u16()?,
+                src: self.u16()?,
+            },
+            20 => ByteCode::Field {
+                dst: self.u16()?,
+                head: self.u16()?,
+                addr: self.u32()?,
+            },
+            21 => ByteCode::SetField {
+                head: self.u16()?,
+                addr: self.u32()?,
+                src: self.u16()?,
+            },
+            22 => ByteCode::Global {
+                dst: self.u16()?,
+                addr: self.u32()?,
+            },
+            23 => ByteCode::SetGlobal {
+                addr: self.u32()?,
+                src: self.u16()?,
+            },
+            24 => {
+                let op = self.u8()?;
+                ByteCode::Binary {
+                    op: *BINARY
+                        .get(op as usize)
+                        .ok_or(LoadError::InvalidOperation(op))?,
+                    dst: self.u16()?,
+                    left: self.u16()?,
+                    right: self.u16()?,
+                }
+            }
+            25 => {
+                let op = self.u8()?;
+                ByteCode::Unary {
+                    op: *UNARY
+                        .get(op as usize)
+                        .ok_or(LoadError::InvalidOperation(op))?,
+                    dst: self.u16()?,
+                    src: self.u16()?,
+                }
+            }
+            26 => ByteCode::Try {
+                addr: self.u32()?,
+                dst: self.u16()?,
+            },
+            27 => ByteCode::EndTry,
+            28 => ByteCode::Throw { src: self.u16()? },
+            opcode => return Err(LoadError::InvalidOpcode(opcode)),
+        })
+    }
+}
+
+/// checks that every register, constant and jump target of `closure` is in range
+fn verify(closure: &Closure) -> Result<(), LoadError> {
+    let code = closure.code.len();
+    let defaults = &closure.defaults;
+    if closure.params as usize + closure.variadic as usize > closure.registers as usize
+        || defaults.len() == 1
+        || defaults.len() > closure.params as usize + 1
+        || defaults.windows(2).any(|pair| pair[0] > pair[1])
+        || defaults.last().is_some_and(|addr| *addr as usize > code)
+    {
+        return Err(LoadError::InvalidParameters);
+    }
+    // execution must never run past the last instruction
+    if !matches!(
+        closure.code.last().map(|bytecode| bytecode.value),
+        Some(ByteCode::Return { .. } | ByteCode::Jump { .. } | ByteCode::Throw { .. })
+    ) {
+        return Err(LoadError::MissingReturn);
+    }
+    let strings = closure.strings.len();
+    for (
+        ip,
+        Located {
+            value: bytecode,
+            pos: _,
+        },
+    ) in closure.code.iter().enumerate()
+    {
+        let ip = ip as Address;
+        let registers = |registers: &[Register]| match registers
+            .iter()
+            .find(|register| **register >= closure.registers)
+        {
+            Some(register) => Err(LoadError::InvalidRegister {
+                addr: ip,
+                register: *register,
+            }),
+            None => Ok(()),
+        };
+        let range = |offset: Register, len: Register| match len {
+            0 => Ok(()),
+            len => registers(&[offset, offset.saturating_add(len - 1)]),
+        };
+        let target = |target: Address, len: usize| {
+            if (target as usize) < len {
+                Ok(())
+            } else {
+                Err(LoadError::InvalidAddress { addr: ip, target })
+            }
+        };
+        match *bytecode {
+            ByteCode::None | ByteCode::EndTry => {}
+            ByteCode::Jump { addr } => target(addr, code)?,
+            ByteCode::Try { addr, dst } => {
+                registers(&[dst])?;
+                target(addr, code)?;
+            }
+            ByteCode::Throw { src } => registers(&[src])?,
+            ByteCode::JumpIf { not: _, cond, addr } => {
+                registers(&[cond])?;
+                target(addr, code)?;
+            }
+            ByteCode::Call {
+                func,
+                offset,
+                args_len,
+                dst,
+            } => {
+                registers(&[func])?;
+                registers(dst.as_slice())?;
+                range(offset, args_len as Register)?;
+            }
+            ByteCode::Return { src } => registers(src.as_slice())?,
+            ByteCode::Move { dst, src } => registers(&[dst, src])?,
+            ByteCode::String { dst, addr } | ByteCode::Global { dst, addr } => {
+                registers(&[dst])?;
+                target(addr, strings)?;
+            }
+            ByteCode::Number { dst, addr } => {
+                registers(&[dst])?;
+                target(addr, closure.numbers.len())?;
+            }
+            ByteCode::Boolean { dst, value: _ }
+            | ByteCode::Null { dst }
+            | ByteCode::Map { dst } => registers(&[dst])?,
+            ByteCode::Closure { dst, addr } => {
+                registers(&[dst])?;
+                target(addr, closure.closures.len())?;
+            }
+            ByteCode::GetUpvalue { dst: reg, addr } | ByteCode::SetUpvalue { addr, src: reg } => {
+                registers(&[reg])?;
+                target(addr, closure.upvalues.len())?;
+            }
+            ByteCode::Close { start } => {
+                if start > closure.registers {
+                    return Err(LoadError::InvalidRegister {
+                        addr: ip,
+                        register: start,
+                    });
+                }
+            }
+            ByteCode::List { dst, offset, len } => {
+                registers(&[dst])?;
+                range(offset, len)?;
+            }
+            ByteCode::IterNew { dst, src } => registers(&[dst, src])?,
+            ByteCode::IterNext { dst, iter, addr } => {
+                registers(&[dst, iter])?;
+                target(addr, code)?;
+            }
+            ByteCode::Index { dst, head, index } => registers(&[dst, head, index])?,
+            ByteCode::SetIndex { head, index, src } => registers(&[head, index, src])?,
+            ByteCode::Field { dst, head, addr } => {
+                registers(&[dst, head])?;
+                target(addr, strings)?;
+            }
+            ByteCode::SetField { head, addr, src } => {
+                registers(&[head, src])?;
+                target(addr, strings)?;
+            }
+            ByteCode::SetGlobal { addr, src } => {
+                registers(&[src])?;
+                target(addr, strings)?;
+            }
+            ByteCode::Binary {
+                op: _,
+                dst,
+                left,
+                right,
+            } => registers(&[dst, left, right])?,
+            ByteCode::Unary { op: _, dst, src } => registers(&[dst, src])?,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use crate::lexer::position::{Located, Span};
+use std::fmt::{Display, Write};
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Span,
+    pub label: Option<String>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+pub trait Diagnose: Display {
+    fn label(&self) -> Option<String> {
+        None
+    }
+    fn notes(&self) -> Vec<String> {
+        vec![]
+    }
+    fn help(&self) -> Option<String> {
+        None
+    }
+}
+impl<E: Diagnose> From<&Located<E>> for Diagnostic {
+    fn from(value: &Located<E>) -> Self {
+        Self {
+            message: value.value.to_string(),
+            span: value.pos.clone(),
+            label: value.value.label(),
+            notes: value.value.notes(),
+            help: value.value.help(),
+        }
+    }
+}
+impl Diagnostic {
+    pub fn render(&self, path: &str, text: &str, color: bool) -> String {
+        let paint = |style: &'static str| if color { style } else { "" };
+        let (red, blue, bold, reset) = (paint(RED), paint(BLUE), paint(BOLD), paint(RESET));
+        let Span { start, end } = &self.span;
+        let ln = (start.ln + 1).to_string();
+        let gutter = " ".repeat(ln.len());
+        let mut out = String::new();
+        let _ = writeln!(out, "{red}error{reset}{bold}: {}{reset}", self.message);
+        let _ = writeln!(
+            out,
+            "{gutter}{blue}-->{reset} {path}:{}:{}",
+            start.ln + 1,
+            start.col + 1
+        );
+        if let Some(line) = text.lines().nth(start.ln) {
+            let width = if end.ln == start.ln {
+                end.col.saturating_sub(start.col)
+            } else {
+                line.chars().count().saturating_sub(start.col)
+            }
+            .max(1);
+            let _ = writeln!(out, "{gutter} {blue}|{reset}");
+            let _ = writeln!(out, "{blue}{ln} |{reset} {line}");
+            // keep tabs so the carets line up with the source however wide a tab is shown
+            let indent: String = line
+                .chars()
+                .take(start.col)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let _ = write!(
+                out,
+                "{gutter} {blue}|{reset} {indent}{red}{}",
+                "^".repeat(width)
+            );
+            if let Some(label) = &self.label {
+                let _ = write!(out, " {label}");
+            }
+            let _ = writeln!(out, "{reset}");
+        }
+        if !self.notes.is_empty() || self.help.is_some() {
+            let _ = writeln!(out, "{gutter} {blue}|{reset}");
+        }
+        for note in &self.notes {
+            let _ = writeln!(out, "{gutter} {blue}={reset} {bold}note{reset}: {note}");
+        }
+        if let Some(help) = &self.help {
+            let _ = writeln!(out, "{gutter} {blue}={reset} {bold}help{reset}: {help}");
+        }
+        out
+    }
+}
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            self.span.start.ln + 1,
+            self.span.start.col + 1,
+            self.message
+        )
+    }
+}
@@ -0,0 +1,194 @@
+use crate::interpreter::{
+    interpreter::NativeError,
+    value::{Function, NativeFunction, Value},
+};
+use std::{collections::HashMap, fmt::Display, rc::Rc};
+
+/// a value that did not have the type a Rust conversion expected
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversionError {
+    pub expected: &'static str,
+    pub got: &'static str,
+}
+impl Display for ConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "expected {}, got {}", self.expected, self.got)
+    }
+}
+impl std::error::Error for ConversionError {}
+impl From<ConversionError> for NativeError {
+    fn from(err: ConversionError) -> Self {
+        Self::Value(err.to_string())
+    }
+}
+
+/// converts a Rust value into a script value
+pub trait IntoValue {
+    fn into_value(self) -> Value;
+}
+/// converts a script value into a Rust value, failing if it has the wrong type
+pub trait FromValue: Sized {
+    fn from_value(value: Value) -> Result<Self, ConversionError>;
+}
+
+fn mismatch<T>(expected: &'static str, value: &Value) -> Result<T, ConversionError> {
+    Err(ConversionError {
+        expected,
+        got: value.typ(),
+    })
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Value {
+        self
+    }
+}
+impl FromValue for Value {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        Ok(value)
+    }
+}
+impl IntoValue for () {
+    fn into_value(self) -> Value {
+        Value::Null
+    }
+}
+impl FromValue for () {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Null => Ok(()),
+            value => mismatch("null", &value),
+        }
+    }
+}
+impl IntoValue for bool {
+    fn into_value(self) -> Value {
+        Value::Boolean(self)
+    }
+}
+impl FromValue for bool {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Boolean(value) => Ok(value),
+            value => mismatch("boolean", &value),
+        }
+    }
+}
+impl IntoValue for f64 {
+    fn into_value(self) -> Value {
+        Value::Number(self)
+    }
+}
+impl FromValue for f64 {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Number(number) => Ok(number),
+            value => mismatch("number", &value),
+        }
+    }
+}
+/// integers convert from numbers without a fractional part that fit into the type
+macro_rules! integer {
+    ($($typ:ty),*) => {$(
+        impl IntoValue for $typ {
+            fn into_value(self) -> Value {
+                Value::Number(self as f64)
+            }
+        }
+        impl FromValue for $typ {
+            fn from_value(value: Value) -> Result<Self, ConversionError> {
+                match value {
+                    Value::Number(number)
+                        if number.fract() == 0.
+                            && number >= <$typ>::MIN as f64
+                            && number < <$typ>::MAX as f64 + 1. =>
+                    {
+                        Ok(number as $typ)
+                    }
+                    value => mismatch(stringify!($typ), &value),
+                }
+            }
+        }
+    )*};
+}
+integer!(i32, i64, u32, u64, usize);
+impl IntoValue for String {
+    fn into_value(self) -> Value {
+        Value::String(self.into())
+    }
+}
+impl IntoValue for &str {
+    fn into_value(self) -> Value {
+        Value::String(self.into())
+    }
+}
+impl FromValue for String {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::String(string) => Ok(string.to_string()),
+            value => mismatch("string", &value),
+        }
+    }
+}
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Value {
+        self.map_or(Value::Null, T::into_value)
+    }
+}
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Null => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Value {
+        self.into_iter()
+            .map(T::into_value)
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::List(list) => list.borrow().iter().cloned().map(T::from_value).collect(),
+            value => mismatch("list", &value),
+        }
+    }
+}
+impl<T: IntoValue> IntoValue for HashMap<String, T> {
+    fn into_value(self) -> Value {
+        self.into_iter()
+            .map(|(key, value)| (key, value.into_value()))
+            .collect::<HashMap<_, _>>()
+            .into()
+    }
+}
+impl<T: FromValue> FromValue for HashMap<String, T> {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Map(map) => map
+                .borrow()
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), T::from_value(value.clone())?)))
+                .collect(),
+            value => mismatch("map", &value),
+        }
+    }
+}
+impl IntoValue for NativeFunction {
+    fn into_value(self) -> Value {
+        Value::Function(Rc::new(Function::NativeFunction(self)))
+    }
+}
+impl FromValue for Rc<Function> {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Function(function) => Ok(function),
+            value => mismatch("function", &value),
+        }
+    }
+}
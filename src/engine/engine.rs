@@ -0,0 +1,179 @@
+//! Embedding API for host applications.
+//!
+//! An [`Engine`] owns an interpreter whose globals persist between calls, so a host can load a
+//! script once and then read its globals or call its functions:
+//!
+//! ```
+//! use compiler::engine::engine::Engine;
+//! use compiler::engine::convert::IntoValue;
+//!
+//! let mut engine = Engine::new();
+//! engine.set_global("base", 10);
+//! engine.eval("def add(a, b = base) { return a + b }").unwrap();
+//! let sum: f64 = engine.call_function("add", vec![1.into_value()]).unwrap();
+//! assert_eq!(sum, 11.);
+//! assert_eq!(engine.eval("add(1, 2)").unwrap(), 3.0.into_value());
+//! ```
+use super::convert::{ConversionError, FromValue, IntoValue};
+use crate::{
+    compiler::compiler::{Compilable, CompileError, Compiler},
+    diagnostic::diagnostic::Diagnostic,
+    interpreter::{
+        interpreter::{Interpreter, NativeError, RunTimeError, TracedError},
+        std::std_globals,
+        value::{NativeFunction, Value},
+    },
+    lexer::{lex, position::Located, tokens::LexError},
+    parser::{ast::ParseError, parse},
+};
+use std::{fmt::Display, fs, io, path::Path, rc::Rc};
+
+/// the path reported in errors of [`Engine::eval`]
+const EVAL_PATH: &str = "<eval>";
+
+#[derive(Debug)]
+pub enum EngineError {
+    Io(io::Error),
+    Syntax {
+        lex: Vec<Located<LexError>>,
+        parse: Vec<Located<ParseError>>,
+    },
+    Compile(Located<CompileError>),
+    Runtime(TracedError),
+    UndefinedGlobal(String),
+    Conversion(ConversionError),
+}
+impl Display for EngineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EngineError::Io(err) => write!(f, "{err}"),
+            EngineError::Syntax { .. } | EngineError::Compile(_) | EngineError::Runtime(_) => {
+                match self.diagnostics().first() {
+                    Some(Diagnostic { message, span, .. }) => {
+                        write!(f, "{}:{}: {message}", span.start.ln + 1, span.start.col + 1)
+                    }
+                    None => write!(f, "invalid syntax"),
+                }
+            }
+            EngineError::UndefinedGlobal(name) => write!(f, "undefined global {name:?}"),
+            EngineError::Conversion(err) => write!(f, "{err}"),
+        }
+    }
+}
+impl std::error::Error for EngineError {}
+impl EngineError {
+    /// the errors in the source, ready to be rendered against its text
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            EngineError::Syntax { lex, parse } => lex
+                .iter()
+                .map(Diagnostic::from)
+                .chain(parse.iter().map(Diagnostic::from))
+                .collect(),
+            EngineError::Compile(err) => vec![Diagnostic::from(err)],
+            EngineError::Runtime(err) => vec![Diagnostic::from(&err.err)],
+            _ => vec![],
+        }
+    }
+}
+impl From<ConversionError> for EngineError {
+    fn from(err: ConversionError) -> Self {
+        Self::Conversion(err)
+    }
+}
+
+/// a script interpreter with the standard library, see the [module docs](self)
+#[derive(Debug)]
+pub struct Engine {
+    pub interpreter: Interpreter,
+    /// forbid assignments to undeclared variables, like `--strict`
+    pub strict: bool,
+}
+impl Default for Engine {
+    fn default() -> Self {
+        let mut interpreter = Interpreter::default();
+        std_globals(&mut interpreter.globals);
+        Self {
+            interpreter,
+            strict: false,
+        }
+    }
+}
+impl Engine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// runs `text`, returning the value of its last expression or `null`
+    ///
+    /// top-level `let` and `def` define globals, so they stay visible to later calls
+    pub fn eval(&mut self, text: &str) -> Result<Value, EngineError> {
+        self.eval_source(EVAL_PATH, text)
+    }
+    /// like [`Engine::eval`] but reads the script from `path`
+    pub fn eval_file(&mut self, path: impl AsRef<Path>) -> Result<Value, EngineError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(EngineError::Io)?;
+        self.eval_source(&path.to_string_lossy(), &text)
+    }
+    fn eval_source(&mut self, path: &str, text: &str) -> Result<Value, EngineError> {
+        let (tokens, lex) = lex(text);
+        let (chunk, parse) = parse(tokens);
+        if !lex.is_empty() || !parse.is_empty() {
+            return Err(EngineError::Syntax { lex, parse });
+        }
+        let mut compiler = Compiler {
+            strict: self.strict,
+            top_level_globals: true,
+            path: path.to_string(),
+            ..Default::default()
+        };
+        let closure = chunk.compile(&mut compiler).map_err(EngineError::Compile)?;
+        let value = self
+            .interpreter
+            .run(&Rc::new(closure))
+            .map_err(EngineError::Runtime)?;
+        Ok(value.unwrap_or_default())
+    }
+    pub fn get_global<T: FromValue>(&self, name: &str) -> Result<T, EngineError> {
+        let value = self
+            .interpreter
+            .globals
+            .get(name)
+            .ok_or_else(|| EngineError::UndefinedGlobal(name.to_string()))?;
+        Ok(T::from_value(value.clone())?)
+    }
+    pub fn set_global(&mut self, name: impl Into<String>, value: impl IntoValue) {
+        self.interpreter
+            .globals
+            .insert(name.into(), value.into_value());
+    }
+    /// calls the global function `name` and converts what it returns
+    pub fn call_function<T: FromValue>(
+        &mut self,
+        name: &str,
+        args: Vec<Value>,
+    ) -> Result<T, EngineError> {
+        let function = match self.get_global(name)? {
+            Value::Function(function) => function,
+            value => {
+                return Err(EngineError::Runtime(TracedError {
+                    err: Located::new(RunTimeError::CannotCall(value.typ()), Default::default()),
+                    trace: vec![],
+                }))
+            }
+        };
+        let value = self
+            .interpreter
+            .run_function(&function, args)
+            .map_err(EngineError::Runtime)?;
+        Ok(T::from_value(value.unwrap_or_default())?)
+    }
+    /// makes a Rust function or closure callable from scripts as the global `name`
+    pub fn register_fn(
+        &mut self,
+        name: impl Into<String>,
+        func: impl Fn(&mut Interpreter, Vec<Value>) -> Result<Value, NativeError> + 'static,
+    ) {
+        self.set_global(name, Rc::new(func) as NativeFunction);
+    }
+}
@@ -1,9 +1,12 @@
-use super::value::{Function, NativeFunction, Value};
+use super::value::{Function, Iter, NativeFunction, Value};
 use crate::{
-    compiler::bytecode::{Address, BinaryOperation, ByteCode, Closure, Register, UnaryOperation},
-    lexer::position::{Located, Position},
+    compiler::bytecode::{
+        Address, BinaryOperation, ByteCode, Closure, Register, UnaryOperation, Upvalue,
+    },
+    diagnostic::diagnostic::Diagnose,
+    lexer::position::{Located, Span},
 };
-use std::{cell::RefCell, collections::HashMap, error::Error, fmt::Display, rc::Rc};
+use std::{cell::RefCell, collections::HashMap, error::Error, fmt::Display, io, rc::Rc};
 
 #[derive(Debug, Default)]
 pub struct Interpreter {
@@ -15,7 +18,15 @@ pub struct CallFrame {
     pub closure: Rc<Closure>,
     pub ip: Address,
     pub stack: Vec<Rc<RefCell<Value>>>,
+    pub upvalues: Vec<Rc<RefCell<Value>>>,
     pub dst: Option<Register>,
+    pub handlers: Vec<Handler>,
+}
+/// an error handler installed by `try`, jumps to `addr` with the error in `dst`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Handler {
+    pub addr: Address,
+    pub dst: Register,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -30,7 +41,60 @@ pub enum RunTimeError {
         right: &'static str,
     },
     CannotCall(&'static str),
-    Custome(String),
+    Index {
+        head: &'static str,
+        index: &'static str,
+    },
+    IndexOutOfRange {
+        index: f64,
+        len: usize,
+    },
+    Field(&'static str),
+    CannotIterate(&'static str),
+    EndOfCode,
+    Arity {
+        expected: Arity,
+        got: u8,
+        name: Option<String>,
+    },
+    WrongType {
+        arg: u8,
+        expected: &'static str,
+        got: &'static str,
+    },
+    Value(String),
+    /// `kind` lets callers tell failures apart, `message` is only for display
+    IO {
+        kind: io::ErrorKind,
+        message: String,
+    },
+    Thrown(Value),
+}
+/// the number of arguments a function accepts, `max` is `None` if there is no upper bound
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arity {
+    pub min: u8,
+    pub max: Option<u8>,
+}
+/// the error a native function can fail with
+#[derive(Debug, Clone, PartialEq)]
+pub enum NativeError {
+    Arity {
+        expected: Arity,
+        got: u8,
+    },
+    /// `arg` is the zero-based index of the offending argument
+    WrongType {
+        arg: u8,
+        expected: &'static str,
+        got: &'static str,
+    },
+    Value(String),
+    /// `kind` lets callers tell failures apart, `message` is only for display
+    IO {
+        kind: io::ErrorKind,
+        message: String,
+    },
 }
 impl Display for RunTimeError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -42,10 +106,155 @@ impl Display for RunTimeError {
                 write!(f, "cannot perform unary operation {op:?} on {right}")
             }
             RunTimeError::CannotCall(typ) => write!(f, "cannot call {typ}"),
-            RunTimeError::Custome(err) => write!(f, "{err}"),
+            RunTimeError::Index { head, index } => write!(f, "cannot index {head} with {index}"),
+            RunTimeError::IndexOutOfRange { index, len } => {
+                write!(f, "index {index} out of range for list of length {len}")
+            }
+            RunTimeError::Field(typ) => write!(f, "cannot access field of {typ}"),
+            RunTimeError::CannotIterate(typ) => write!(f, "cannot iterate over {typ}"),
+            RunTimeError::EndOfCode => write!(f, "execution ran past the end of the code"),
+            RunTimeError::Arity {
+                expected,
+                got,
+                name: Some(name),
+            } => write!(f, "{name} expected {expected}, got {got}"),
+            RunTimeError::Arity {
+                expected,
+                got,
+                name: None,
+            } => write!(f, "expected {expected}, got {got}"),
+            RunTimeError::WrongType { arg, expected, got } => {
+                write!(f, "expected {expected} for argument #{}, got {got}", *arg as usize + 1)
+            }
+            RunTimeError::Value(msg) => write!(f, "{msg}"),
+            RunTimeError::IO { kind: _, message } => write!(f, "io error: {message}"),
+            RunTimeError::Thrown(value) => write!(f, "uncaught error: {value}"),
+        }
+    }
+}
+impl Arity {
+    pub fn exact(count: u8) -> Self {
+        Self {
+            min: count,
+            max: Some(count),
+        }
+    }
+    pub fn range(min: u8, max: u8) -> Self {
+        Self {
+            min,
+            max: Some(max),
+        }
+    }
+    pub fn at_least(min: u8) -> Self {
+        Self { min, max: None }
+    }
+    pub fn accepts(&self, count: u8) -> bool {
+        count >= self.min && self.max.is_none_or(|max| count <= max)
+    }
+}
+impl Display for Arity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plural = |count: u8| if count == 1 { "" } else { "s" };
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{max} argument{}", plural(max)),
+            Some(max) => write!(f, "{} to {max} arguments", self.min),
+            None => write!(f, "at least {} argument{}", self.min, plural(self.min)),
+        }
+    }
+}
+impl Display for NativeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        RunTimeError::from(self.clone()).fmt(f)
+    }
+}
+impl Error for NativeError {}
+impl From<io::Error> for NativeError {
+    fn from(err: io::Error) -> Self {
+        Self::IO {
+            kind: err.kind(),
+            message: err.to_string(),
+        }
+    }
+}
+impl From<NativeError> for RunTimeError {
+    fn from(err: NativeError) -> Self {
+        match err {
+            NativeError::Arity { expected, got } => Self::Arity {
+                expected,
+                got,
+                name: None,
+            },
+            NativeError::WrongType { arg, expected, got } => Self::WrongType { arg, expected, got },
+            NativeError::Value(msg) => Self::Value(msg),
+            NativeError::IO { kind, message } => Self::IO { kind, message },
+        }
+    }
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceFrame {
+    pub name: String,
+    pub path: String,
+    pub pos: Span,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct TracedError {
+    pub err: Located<RunTimeError>,
+    /// the call stack at the time of the error, innermost call first
+    pub trace: Vec<TraceFrame>,
+}
+impl Display for TraceFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({}:{}:{})",
+            self.name,
+            self.path,
+            self.pos.start.ln + 1,
+            self.pos.start.col + 1
+        )
+    }
+}
+impl Diagnose for RunTimeError {
+    fn notes(&self) -> Vec<String> {
+        match self {
+            RunTimeError::IndexOutOfRange { index: _, len } if *len > 0 => {
+                vec![format!("valid indices are 0 to {}", len - 1)]
+            }
+            RunTimeError::IndexOutOfRange { .. } => vec!["the list is empty".into()],
+            _ => vec![],
         }
     }
 }
+/// the arguments `closure` accepts, leaving out default parameters and collecting the rest
+pub fn arity(closure: &Closure) -> Arity {
+    Arity {
+        min: closure.params - closure.defaults.len().saturating_sub(1) as u8,
+        max: (!closure.variadic).then_some(closure.params),
+    }
+}
+/// the value a `catch` receives: thrown values as they are, built-in errors as a map
+/// with their `message`, `line` and `column`, io errors also with their `kind`
+pub fn error_value(Located { value: err, pos }: &Located<RunTimeError>) -> Value {
+    if let RunTimeError::Thrown(value) = err {
+        return value.clone();
+    }
+    let mut map = HashMap::from([
+        ("message".to_string(), Value::String(err.to_string().into())),
+        ("line".to_string(), Value::Number((pos.start.ln + 1) as f64)),
+        ("column".to_string(), Value::Number((pos.start.col + 1) as f64)),
+    ]);
+    if let RunTimeError::IO { kind, message: _ } = err {
+        map.insert("kind".to_string(), Value::String(format!("{kind:?}").into()));
+    }
+    map.into()
+}
+fn list_index(index: f64) -> Option<usize> {
+    if index >= 0. && index.fract() == 0. {
+        Some(index as usize)
+    } else {
+        None
+    }
+}
 impl CallFrame {
     pub fn instr(&self) -> Option<&Located<ByteCode>> {
         self.closure.code.get(self.ip as usize)
@@ -53,6 +262,17 @@ impl CallFrame {
     pub fn register(&self, register: Register) -> Option<&Rc<RefCell<Value>>> {
         self.stack.get(register as usize)
     }
+    pub fn upvalue(&self, addr: Address) -> Option<&Rc<RefCell<Value>>> {
+        self.upvalues.get(addr as usize)
+    }
+    /// gives every captured register from `start` on a fresh cell, so closures keep the old one
+    pub fn close(&mut self, start: Register) {
+        for cell in self.stack.iter_mut().skip(start as usize) {
+            if Rc::strong_count(cell) > 1 {
+                *cell = Rc::new(RefCell::new(Value::default()));
+            }
+        }
+    }
 }
 impl Interpreter {
     pub fn call_frame(&self) -> Option<&CallFrame> {
@@ -65,48 +285,77 @@ impl Interpreter {
         self.call_frame_mut()?
             .register(register)
     }
-    pub fn call_closure(&mut self, closure: &Rc<Closure>, args: Vec<Value>, dst: Option<Register>) {
+    pub fn call_closure(
+        &mut self,
+        closure: &Rc<Closure>,
+        upvalues: Vec<Rc<RefCell<Value>>>,
+        args: Vec<Value>,
+        dst: Option<Register>,
+    ) -> Result<(), RunTimeError> {
+        let expected = arity(closure);
+        let got = u8::try_from(args.len()).unwrap_or(u8::MAX);
+        if !expected.accepts(got) {
+            return Err(RunTimeError::Arity {
+                expected,
+                got,
+                name: Some(closure.name.clone()),
+            });
+        }
+        let ip = closure.entry(args.len());
+        let mut args = args.into_iter();
         let mut stack = Vec::with_capacity(closure.registers as usize + 1);
-        let args = &args[0..stack.capacity().min(args.len())];
-        let args_len = args.len();
-        stack.extend(args.iter().map(|v| Rc::new(RefCell::new(v.clone()))));
         stack.extend(
-            (args_len..=closure.registers as usize).map(|_| Rc::new(RefCell::new(Value::default()))),
+            args.by_ref()
+                .take(closure.params as usize)
+                .map(|v| Rc::new(RefCell::new(v))),
+        );
+        stack.resize_with(closure.params as usize, Default::default);
+        if closure.variadic {
+            stack.push(Rc::new(RefCell::new(Value::from(args.collect::<Vec<_>>()))));
+        }
+        let len = stack.len();
+        stack.extend(
+            (len..=closure.registers as usize).map(|_| Rc::new(RefCell::new(Value::default()))),
         );
         self.call_stack.push(CallFrame {
             closure: Rc::clone(closure),
-            ip: 0,
+            ip,
             stack,
+            upvalues,
             dst,
+            handlers: vec![],
         });
+        Ok(())
     }
     pub fn call_native(
         &mut self,
         func: &NativeFunction,
         args: Vec<Value>,
-        pos: &Position,
-    ) -> Result<Value, Located<Box<dyn Error>>> {
+        pos: &Span,
+    ) -> Result<Value, Located<NativeError>> {
         func(self, args).map_err(|err| Located::new(err, pos.clone()))
     }
     pub fn call(
         &mut self,
         function: &Function,
         args: Vec<Value>,
-        pos: &Position,
+        pos: &Span,
         dst: Option<Register>,
-    ) -> Result<(), Located<Box<dyn Error>>> {
+    ) -> Result<(), Located<RunTimeError>> {
         match function {
             Function::NativeFunction(func) => {
-                let value = self.call_native(func, args, pos)?;
+                let value = self
+                    .call_native(func, args, pos)
+                    .map_err(|err| err.map(RunTimeError::from))?;
                 if let Some(dst) = dst {
                     let mut dst = self.register(dst).expect("location not found").borrow_mut();
                     *dst = value;
                 }
                 Ok(())
             }
-            Function::Function(closure) => {
-                self.call_closure(closure, args, dst);
-                Ok(())
+            Function::Function { closure, upvalues } => {
+                self.call_closure(closure, upvalues.clone(), args, dst)
+                    .map_err(|err| Located::new(err, pos.clone()))
             }
         }
     }
@@ -139,15 +388,20 @@ impl Interpreter {
         None
     }
     pub fn step(&mut self) -> Result<Option<Value>, Located<RunTimeError>> {
-        let Located {
+        let frame = self.call_frame().expect("no call frame on stack");
+        let Some(Located {
             value: bytecode,
             pos,
-        } = self
-            .call_frame()
-            .expect("no call frame on stack")
-            .instr()
-            .expect("ip out of range")
-            .clone();
+        }) = frame.instr().cloned()
+        else {
+            let pos = frame
+                .closure
+                .code
+                .last()
+                .map(|bytecode| bytecode.pos.clone())
+                .unwrap_or_default();
+            return Err(Located::new(RunTimeError::EndOfCode, pos));
+        };
         self.call_frame_mut().expect("no call frame on stack").ip += 1;
         match bytecode {
             ByteCode::None => {}
@@ -202,13 +456,32 @@ impl Interpreter {
                 }));
                 match func {
                     Value::Function(function) => {
-                        self.call(&function, args, &pos, dst)
-                            .map_err(|err| err.map(|err| RunTimeError::Custome(err.to_string())))?;
+                        self.call(&function, args, &pos, dst)?;
                     }
                     value => return Err(Located::new(RunTimeError::CannotCall(value.typ()), pos)),
                 }
             }
             ByteCode::Return { src } => return Ok(self.return_call(src)),
+            ByteCode::Try { addr, dst } => {
+                self.call_frame_mut()
+                    .expect("no call frame on stack")
+                    .handlers
+                    .push(Handler { addr, dst });
+            }
+            ByteCode::EndTry => {
+                self.call_frame_mut()
+                    .expect("no call frame on stack")
+                    .handlers
+                    .pop();
+            }
+            ByteCode::Throw { src } => {
+                let value = self
+                    .register(src)
+                    .expect("register not found")
+                    .borrow()
+                    .clone();
+                return Err(Located::new(RunTimeError::Thrown(value), pos));
+            }
             ByteCode::Move { dst, src } => {
                 let src = self
                     .register(src)
@@ -235,21 +508,234 @@ impl Interpreter {
                     .expect("no call frame on stack")
                     .closure
                     .number(addr)
-                    .expect("number not found")
-                    .clone();
+                    .copied()
+                    .expect("number not found");
                 let mut dst = self.register(dst).expect("register not found").borrow_mut();
                 *dst = Value::Number(number);
             }
+            ByteCode::Boolean { dst, value } => {
+                let mut dst = self.register(dst).expect("register not found").borrow_mut();
+                *dst = Value::Boolean(value);
+            }
+            ByteCode::Null { dst } => {
+                let mut dst = self.register(dst).expect("register not found").borrow_mut();
+                *dst = Value::Null;
+            }
             ByteCode::Closure { dst, addr } => {
-                let closure = Rc::clone(
-                    self.call_frame()
-                        .expect("no call frame on stack")
-                        .closure
-                        .closure(addr)
-                        .expect("closure not found"),
-                );
+                let frame = self.call_frame().expect("no call frame on stack");
+                let closure = Rc::clone(frame.closure.closure(addr).expect("closure not found"));
+                let upvalues = closure
+                    .upvalues
+                    .iter()
+                    .map(|upvalue| match *upvalue {
+                        Upvalue::Local(reg) => {
+                            Rc::clone(frame.register(reg).expect("register not found"))
+                        }
+                        Upvalue::Upvalue(addr) => {
+                            Rc::clone(frame.upvalue(addr).expect("upvalue not found"))
+                        }
+                    })
+                    .collect();
                 let mut dst = self.register(dst).expect("register not found").borrow_mut();
-                *dst = Value::Function(Rc::new(Function::Function(closure)));
+                *dst = Value::Function(Rc::new(Function::Function { closure, upvalues }));
+            }
+            ByteCode::GetUpvalue { dst, addr } => {
+                let value = self
+                    .call_frame()
+                    .expect("no call frame on stack")
+                    .upvalue(addr)
+                    .expect("upvalue not found")
+                    .borrow()
+                    .clone();
+                let mut dst = self.register(dst).expect("register not found").borrow_mut();
+                *dst = value;
+            }
+            ByteCode::SetUpvalue { addr, src } => {
+                let value = self.register(src).expect("register not found").borrow().clone();
+                let mut dst = self
+                    .call_frame()
+                    .expect("no call frame on stack")
+                    .upvalue(addr)
+                    .expect("upvalue not found")
+                    .borrow_mut();
+                *dst = value;
+            }
+            ByteCode::Close { start } => {
+                self.call_frame_mut()
+                    .expect("no call frame on stack")
+                    .close(start);
+            }
+            ByteCode::List { dst, offset, len } => {
+                let list: Vec<Value> = (offset..offset + len)
+                    .map(|reg| {
+                        self.register(reg)
+                            .expect("register not found")
+                            .borrow()
+                            .clone()
+                    })
+                    .collect();
+                let mut dst = self.register(dst).expect("register not found").borrow_mut();
+                *dst = list.into();
+            }
+            ByteCode::Map { dst } => {
+                let mut dst = self.register(dst).expect("register not found").borrow_mut();
+                *dst = HashMap::new().into();
+            }
+            ByteCode::IterNew { dst, src } => {
+                let value = self
+                    .register(src)
+                    .expect("register not found")
+                    .borrow()
+                    .clone();
+                let iter = match value {
+                    Value::Iterator(iter) => Value::Iterator(iter),
+                    Value::List(list) => Iter::List { list, index: 0 }.into(),
+                    Value::String(string) => Iter::String { string, index: 0 }.into(),
+                    Value::Map(map) => Iter::Keys {
+                        keys: map.borrow().keys().cloned().collect(),
+                        index: 0,
+                    }
+                    .into(),
+                    value => {
+                        return Err(Located::new(RunTimeError::CannotIterate(value.typ()), pos))
+                    }
+                };
+                let mut dst = self.register(dst).expect("register not found").borrow_mut();
+                *dst = iter;
+            }
+            ByteCode::IterNext { dst, iter, addr } => {
+                let iter = match self
+                    .register(iter)
+                    .expect("register not found")
+                    .borrow()
+                    .clone()
+                {
+                    Value::Iterator(iter) => iter,
+                    value => {
+                        return Err(Located::new(RunTimeError::CannotIterate(value.typ()), pos))
+                    }
+                };
+                let value = iter.borrow_mut().next();
+                if let Some(value) = value {
+                    let mut dst = self.register(dst).expect("register not found").borrow_mut();
+                    *dst = value;
+                } else {
+                    self.call_frame_mut().expect("no call frame on stack").ip = addr;
+                }
+            }
+            ByteCode::Index { dst, head, index } => {
+                let head = self
+                    .register(head)
+                    .expect("register not found")
+                    .borrow()
+                    .clone();
+                let index = self
+                    .register(index)
+                    .expect("register not found")
+                    .borrow()
+                    .clone();
+                let value = match (head, index) {
+                    (Value::List(list), Value::Number(index)) => list_index(index)
+                        .and_then(|index| list.borrow().get(index).cloned())
+                        .unwrap_or_default(),
+                    (Value::String(string), Value::Number(index)) => list_index(index)
+                        .and_then(|index| string.chars().nth(index))
+                        .map(|c| Value::String(c.to_string().into()))
+                        .unwrap_or_default(),
+                    (Value::Map(map), Value::String(key)) => {
+                        map.borrow().get(key.as_ref()).cloned().unwrap_or_default()
+                    }
+                    (head, index) => {
+                        return Err(Located::new(
+                            RunTimeError::Index {
+                                head: head.typ(),
+                                index: index.typ(),
+                            },
+                            pos,
+                        ))
+                    }
+                };
+                let mut dst = self.register(dst).expect("register not found").borrow_mut();
+                *dst = value;
+            }
+            ByteCode::SetIndex { head, index, src } => {
+                let head = self
+                    .register(head)
+                    .expect("register not found")
+                    .borrow()
+                    .clone();
+                let index = self
+                    .register(index)
+                    .expect("register not found")
+                    .borrow()
+                    .clone();
+                let value = self.register(src).expect("register not found").borrow().clone();
+                match (head, index) {
+                    (Value::List(list), Value::Number(index)) => {
+                        let mut list = list.borrow_mut();
+                        let len = list.len();
+                        let Some(dst) = list_index(index).and_then(|index| list.get_mut(index))
+                        else {
+                            return Err(Located::new(
+                                RunTimeError::IndexOutOfRange { index, len },
+                                pos,
+                            ));
+                        };
+                        *dst = value;
+                    }
+                    (Value::Map(map), Value::String(key)) => {
+                        map.borrow_mut().insert(key.to_string(), value);
+                    }
+                    (head, index) => {
+                        return Err(Located::new(
+                            RunTimeError::Index {
+                                head: head.typ(),
+                                index: index.typ(),
+                            },
+                            pos,
+                        ))
+                    }
+                }
+            }
+            ByteCode::Field { dst, head, addr } => {
+                let head = self
+                    .register(head)
+                    .expect("register not found")
+                    .borrow()
+                    .clone();
+                let field = self
+                    .call_frame()
+                    .expect("no call frame on stack")
+                    .closure
+                    .string(addr)
+                    .expect("string not found");
+                let value = match head {
+                    Value::Map(map) => map.borrow().get(field).cloned().unwrap_or_default(),
+                    head => return Err(Located::new(RunTimeError::Field(head.typ()), pos)),
+                };
+                let mut dst = self.register(dst).expect("register not found").borrow_mut();
+                *dst = value;
+            }
+            ByteCode::SetField { head, addr, src } => {
+                let head = self
+                    .register(head)
+                    .expect("register not found")
+                    .borrow()
+                    .clone();
+                let value = self.register(src).expect("register not found").borrow().clone();
+                let field = self
+                    .call_frame()
+                    .expect("no call frame on stack")
+                    .closure
+                    .string(addr)
+                    .expect("string not found")
+                    .clone();
+                match head {
+                    Value::Map(map) => {
+                        map.borrow_mut().insert(field, value);
+                    }
+                    head => return Err(Located::new(RunTimeError::Field(head.typ()), pos)),
+                }
             }
             ByteCode::Global { dst, addr } => {
                 let value = {
@@ -302,6 +788,9 @@ impl Interpreter {
                 *dst = match op {
                     BinaryOperation::Add => match (left, right) {
                         (Value::Number(a), Value::Number(b)) => Value::Number(a + b),
+                        (Value::String(a), Value::String(b)) => {
+                            Value::String(format!("{a}{b}").into())
+                        }
                         (left, right) => {
                             return Err(Located::new(
                                 RunTimeError::Binary {
@@ -432,8 +921,20 @@ impl Interpreter {
                             ))
                         }
                     },
-                    BinaryOperation::And => Value::Boolean(bool::from(&left) && bool::from(&right)),
-                    BinaryOperation::Or => Value::Boolean(bool::from(&left) || bool::from(&right)),
+                    BinaryOperation::And => {
+                        if bool::from(&left) {
+                            right
+                        } else {
+                            left
+                        }
+                    }
+                    BinaryOperation::Or => {
+                        if bool::from(&left) {
+                            left
+                        } else {
+                            right
+                        }
+                    }
                 };
             }
             ByteCode::Unary { op, dst, src } => {
@@ -454,16 +955,90 @@ impl Interpreter {
                         }
                     },
                     UnaryOperation::Not => Value::Boolean(!bool::from(&right)),
+                    UnaryOperation::ToString => Value::String(right.to_string().into()),
                 }
             }
         }
         Ok(None)
     }
-    pub fn run(&mut self, closure: &Rc<Closure>) -> Result<Option<Value>, Located<RunTimeError>> {
+    pub fn trace(&self) -> Vec<TraceFrame> {
+        self.call_stack
+            .iter()
+            .rev()
+            .map(|frame| TraceFrame {
+                name: frame.closure.name.clone(),
+                path: frame.closure.path.clone(),
+                pos: frame
+                    .closure
+                    .code
+                    .get(frame.ip.saturating_sub(1) as usize)
+                    .map(|bytecode| bytecode.pos.clone())
+                    .unwrap_or_default(),
+            })
+            .collect()
+    }
+    /// unwinds the call stack above `offset` to the innermost error handler and jumps to it,
+    /// returns false if there is none
+    pub fn catch(&mut self, err: &Located<RunTimeError>, offset: usize) -> bool {
+        let Some(depth) = (offset..self.call_stack.len())
+            .rev()
+            .find(|depth| !self.call_stack[*depth].handlers.is_empty())
+        else {
+            return false;
+        };
+        self.call_stack.truncate(depth + 1);
+        let frame = self.call_frame_mut().expect("no call frame on stack");
+        let Handler { addr, dst } = frame.handlers.pop().expect("no handler in frame");
+        frame.ip = addr;
+        // the body may have been left halfway through, close what it captured before `dst` is
+        // reused for the error
+        frame.close(dst);
+        *frame.stack.get_mut(dst as usize).expect("register not found") =
+            Rc::new(RefCell::new(error_value(err)));
+        true
+    }
+    pub fn run(&mut self, closure: &Rc<Closure>) -> Result<Option<Value>, TracedError> {
+        let function = Function::Function {
+            closure: Rc::clone(closure),
+            upvalues: vec![],
+        };
+        self.run_function(&function, vec![])
+    }
+    /// calls `function` with `args` and runs it to completion
+    pub fn run_function(
+        &mut self,
+        function: &Function,
+        args: Vec<Value>,
+    ) -> Result<Option<Value>, TracedError> {
         let offset = self.call_stack.len();
-        self.call_closure(closure, vec![], None);
+        let called = match function {
+            Function::NativeFunction(func) => {
+                return func(self, args).map(Some).map_err(|err| TracedError {
+                    err: Located::new(err.into(), Span::default()),
+                    trace: vec![],
+                })
+            }
+            Function::Function { closure, upvalues } => {
+                self.call_closure(closure, upvalues.clone(), args, None)
+            }
+        };
+        if let Err(err) = called {
+            return Err(TracedError {
+                err: Located::new(err, Span::default()),
+                trace: vec![],
+            });
+        }
         loop {
-            let value = self.step()?;
+            let value = match self.step() {
+                Ok(value) => value,
+                Err(err) if self.catch(&err, offset) => continue,
+                Err(err) => {
+                    let mut trace = self.trace();
+                    trace.truncate(self.call_stack.len() - offset);
+                    self.call_stack.truncate(offset);
+                    return Err(TracedError { err, trace });
+                }
+            };
             if self.call_stack.len() <= offset || self.call_stack.is_empty() {
                 return Ok(value);
             }
@@ -1,20 +1,112 @@
 use super::{
-    interpreter::Interpreter,
-    value::{Function, Value},
+    interpreter::{Arity, Interpreter, NativeError},
+    value::{Function, Iter, Value},
+};
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    rc::Rc,
 };
-use std::{collections::HashMap, error::Error, rc::Rc};
 
 pub fn std_globals(globals: &mut HashMap<String, Value>) {
     globals.insert(
         "print".into(),
-        Value::Function(Rc::new(Function::NativeFunction(_print))),
+        Value::Function(Rc::new(Function::NativeFunction(Rc::new(_print)))),
+    );
+    globals.insert(
+        "tostring".into(),
+        Value::Function(Rc::new(Function::NativeFunction(Rc::new(_tostring)))),
+    );
+    globals.insert(
+        "len".into(),
+        Value::Function(Rc::new(Function::NativeFunction(Rc::new(_len)))),
+    );
+    globals.insert(
+        "push".into(),
+        Value::Function(Rc::new(Function::NativeFunction(Rc::new(_push)))),
+    );
+    globals.insert(
+        "range".into(),
+        Value::Function(Rc::new(Function::NativeFunction(Rc::new(_range)))),
     );
 }
 
-fn _print(_: &mut Interpreter, args: Vec<Value>) -> Result<Value, Box<dyn Error>> {
+fn arity(args: &[Value], expected: Arity) -> Result<(), NativeError> {
+    let got = u8::try_from(args.len()).unwrap_or(u8::MAX);
+    if expected.accepts(got) {
+        Ok(())
+    } else {
+        Err(NativeError::Arity { expected, got })
+    }
+}
+
+fn _print(_: &mut Interpreter, args: Vec<Value>) -> Result<Value, NativeError> {
+    let mut stdout = io::stdout().lock();
     for arg in args {
-        print!("{}", arg);
+        write!(stdout, "{}", arg)?;
     }
-    println!();
+    writeln!(stdout)?;
     Ok(Value::default())
 }
+
+fn _tostring(_: &mut Interpreter, args: Vec<Value>) -> Result<Value, NativeError> {
+    arity(&args, Arity::exact(1))?;
+    Ok(Value::String(args[0].to_string().into()))
+}
+
+fn _len(_: &mut Interpreter, args: Vec<Value>) -> Result<Value, NativeError> {
+    arity(&args, Arity::exact(1))?;
+    match &args[0] {
+        Value::String(string) => Ok(Value::Number(string.chars().count() as f64)),
+        Value::List(list) => Ok(Value::Number(list.borrow().len() as f64)),
+        Value::Map(map) => Ok(Value::Number(map.borrow().len() as f64)),
+        value => Err(NativeError::WrongType {
+            arg: 0,
+            expected: "string, list or map",
+            got: value.typ(),
+        }),
+    }
+}
+
+fn _push(_: &mut Interpreter, args: Vec<Value>) -> Result<Value, NativeError> {
+    arity(&args, Arity::at_least(1))?;
+    let mut args = args.into_iter();
+    match args.next() {
+        Some(Value::List(list)) => {
+            list.borrow_mut().extend(args);
+            Ok(Value::default())
+        }
+        value => Err(NativeError::WrongType {
+            arg: 0,
+            expected: "list",
+            got: value.unwrap_or_default().typ(),
+        }),
+    }
+}
+
+fn _range(_: &mut Interpreter, args: Vec<Value>) -> Result<Value, NativeError> {
+    arity(&args, Arity::range(1, 3))?;
+    let mut numbers = Vec::with_capacity(args.len());
+    for (arg, value) in (0..).zip(args) {
+        match value {
+            Value::Number(number) => numbers.push(number),
+            value => {
+                return Err(NativeError::WrongType {
+                    arg,
+                    expected: "number",
+                    got: value.typ(),
+                })
+            }
+        }
+    }
+    let (current, end, step) = match numbers[..] {
+        [end] => (0., end, 1.),
+        [start, end] => (start, end, 1.),
+        [start, end, step] => (start, end, step),
+        _ => unreachable!("arity already checked"),
+    };
+    if step == 0. {
+        return Err(NativeError::Value("range step cannot be zero".into()));
+    }
+    Ok(Iter::Range { current, end, step }.into())
+}
@@ -1,26 +1,53 @@
-use super::interpreter::Interpreter;
+use super::interpreter::{Interpreter, NativeError};
 use crate::compiler::bytecode::Closure;
 use std::{
-    error::Error,
+    cell::RefCell,
+    collections::HashMap,
     fmt::{Debug, Display},
     rc::Rc,
 };
 
-#[derive(Clone, PartialEq, Default)]
+#[derive(Clone, Default)]
 pub enum Value {
     #[default]
     Null,
     Number(f64),
     Boolean(bool),
     String(Rc<str>),
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<HashMap<String, Value>>>),
     Function(Rc<Function>),
+    Iterator(Rc<RefCell<Iter>>),
 }
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Clone)]
 pub enum Function {
     NativeFunction(NativeFunction),
-    Function(Rc<Closure>),
+    Function {
+        closure: Rc<Closure>,
+        upvalues: Vec<Rc<RefCell<Value>>>,
+    },
 }
-pub type NativeFunction = fn(&mut Interpreter, Vec<Value>) -> Result<Value, Box<dyn Error>>;
+#[derive(Debug, Clone)]
+pub enum Iter {
+    Range {
+        current: f64,
+        end: f64,
+        step: f64,
+    },
+    List {
+        list: Rc<RefCell<Vec<Value>>>,
+        index: usize,
+    },
+    String {
+        string: Rc<str>,
+        index: usize,
+    },
+    Keys {
+        keys: Vec<String>,
+        index: usize,
+    },
+}
+pub type NativeFunction = Rc<dyn Fn(&mut Interpreter, Vec<Value>) -> Result<Value, NativeError>>;
 
 impl Value {
     pub fn typ(&self) -> &'static str {
@@ -29,7 +56,100 @@ impl Value {
             Value::Number(_) => "number",
             Value::Boolean(_) => "boolean",
             Value::String(_) => "string",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
             Value::Function(_) => "function",
+            Value::Iterator(_) => "iterator",
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::List(a), Value::List(b)) => Rc::ptr_eq(a, b),
+            (Value::Map(a), Value::Map(b)) => Rc::ptr_eq(a, b),
+            (Value::Function(a), Value::Function(b)) => a == b,
+            (Value::Iterator(a), Value::Iterator(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::NativeFunction(a), Self::NativeFunction(b)) => Rc::ptr_eq(a, b),
+            (
+                Self::Function {
+                    closure: a,
+                    upvalues: a_upvalues,
+                },
+                Self::Function {
+                    closure: b,
+                    upvalues: b_upvalues,
+                },
+            ) => {
+                Rc::ptr_eq(a, b)
+                    && a_upvalues.len() == b_upvalues.len()
+                    && a_upvalues
+                        .iter()
+                        .zip(b_upvalues)
+                        .all(|(a, b)| Rc::ptr_eq(a, b))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NativeFunction(func) => f
+                .debug_tuple("NativeFunction")
+                .field(&Rc::as_ptr(func))
+                .finish(),
+            Self::Function { closure, upvalues } => f
+                .debug_struct("Function")
+                .field("closure", closure)
+                .field("upvalues", upvalues)
+                .finish(),
+        }
+    }
+}
+
+impl Iterator for Iter {
+    type Item = Value;
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Iter::Range { current, end, step } => {
+                if (*step > 0. && *current < *end) || (*step < 0. && *current > *end) {
+                    let value = *current;
+                    *current += *step;
+                    Some(Value::Number(value))
+                } else {
+                    None
+                }
+            }
+            Iter::List { list, index } => {
+                let value = list.borrow().get(*index).cloned()?;
+                *index += 1;
+                Some(value)
+            }
+            Iter::String { string, index } => {
+                let c = string[*index..].chars().next()?;
+                *index += c.len_utf8();
+                Some(Value::String(c.to_string().into()))
+            }
+            Iter::Keys { keys, index } => {
+                let key = keys.get(*index)?;
+                *index += 1;
+                Some(Value::String(key.as_str().into()))
+            }
         }
     }
 }
@@ -45,15 +165,65 @@ impl Display for Value {
 }
 impl Debug for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.debug(f, &mut vec![])
+    }
+}
+impl Value {
+    /// `seen` holds the lists and maps currently being printed, a value that contains itself
+    /// prints as `[...]` or `{...}` where it repeats
+    fn debug(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        seen: &mut Vec<*const ()>,
+    ) -> std::fmt::Result {
         match self {
             Value::Null => write!(f, "null"),
             Value::Number(number) => write!(f, "{number:?}"),
             Value::Boolean(bool) => write!(f, "{bool:?}"),
             Value::String(string) => write!(f, "{string:?}"),
+            Value::List(list) => {
+                let ptr = Rc::as_ptr(list) as *const ();
+                if seen.contains(&ptr) {
+                    return write!(f, "[...]");
+                }
+                seen.push(ptr);
+                write!(f, "[")?;
+                for (i, value) in list.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    value.debug(f, seen)?;
+                }
+                seen.pop();
+                write!(f, "]")
+            }
+            Value::Map(map) => {
+                let ptr = Rc::as_ptr(map) as *const ();
+                if seen.contains(&ptr) {
+                    return write!(f, "{{...}}");
+                }
+                seen.push(ptr);
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{key} = ")?;
+                    value.debug(f, seen)?;
+                }
+                seen.pop();
+                write!(f, "}}")
+            }
             Value::Function(func) => write!(f, "function:{:08x?}", Rc::as_ptr(func)),
+            Value::Iterator(iter) => write!(f, "iterator:{:08x?}", Rc::as_ptr(iter)),
         }
     }
 }
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::Boolean(value)
+    }
+}
 impl From<f64> for Value {
     fn from(value: f64) -> Self {
         Self::Number(value)
@@ -69,6 +239,21 @@ impl From<&str> for Value {
         Self::String(value.into())
     }
 }
+impl From<Vec<Value>> for Value {
+    fn from(value: Vec<Value>) -> Self {
+        Self::List(Rc::new(RefCell::new(value)))
+    }
+}
+impl From<HashMap<String, Value>> for Value {
+    fn from(value: HashMap<String, Value>) -> Self {
+        Self::Map(Rc::new(RefCell::new(value)))
+    }
+}
+impl From<Iter> for Value {
+    fn from(value: Iter) -> Self {
+        Self::Iterator(Rc::new(RefCell::new(value)))
+    }
+}
 impl From<NativeFunction> for Value {
     fn from(value: NativeFunction) -> Self {
         Self::Function(Rc::new(Function::NativeFunction(value)))
@@ -76,18 +261,31 @@ impl From<NativeFunction> for Value {
 }
 impl From<Closure> for Value {
     fn from(value: Closure) -> Self {
-        Self::Function(Rc::new(Function::Function(Rc::new(value))))
+        Self::Function(Rc::new(Function::Function {
+            closure: Rc::new(value),
+            upvalues: vec![],
+        }))
     }
 }
 
+/// Truthiness of a value, used by `JumpIf`, `!`, `&` and `|`.
+///
+/// - `null` is falsy
+/// - booleans are their own value
+/// - numbers are truthy unless they are `0` or `NaN`
+/// - strings, lists and maps are truthy unless they are empty
+/// - functions and iterators are always truthy
 impl From<&Value> for bool {
     fn from(value: &Value) -> bool {
         match value {
             Value::Null => false,
-            Value::Number(v) => *v == 0.,
+            Value::Number(v) => *v != 0. && !v.is_nan(),
             Value::Boolean(v) => *v,
             Value::String(v) => !v.is_empty(),
+            Value::List(v) => !v.borrow().is_empty(),
+            Value::Map(v) => !v.borrow().is_empty(),
             Value::Function(_) => true,
+            Value::Iterator(_) => true,
         }
     }
 }
@@ -1,17 +1,19 @@
 use std::{iter::Peekable, str::Chars};
 
-use super::position::{Located, Position};
+use super::position::{Located, Position, Span};
 
 #[derive(Debug, Clone)]
 pub struct Lexer<'a> {
     pub chars: Peekable<Chars<'a>>,
     pub ln: usize,
     pub col: usize,
+    pub offset: usize,
 }
 impl<'a> Iterator for Lexer<'a> {
     type Item = char;
     fn next(&mut self) -> Option<Self::Item> {
         let c = self.chars.next()?;
+        self.offset += c.len_utf8();
         if c == '\n' {
             self.ln += 1;
             self.col = 0;
@@ -25,23 +27,46 @@ impl<'a> Lexer<'a> {
     pub fn peek(&mut self) -> Option<&char> {
         self.chars.peek()
     }
+    pub fn peek_nth(&self, n: usize) -> Option<char> {
+        self.chars.clone().nth(n)
+    }
     pub fn pos(&self) -> Position {
-        Position::new(self.ln, self.col)
+        Position::new(self.ln, self.col, self.offset)
+    }
+    pub fn char_span(&mut self) -> Span {
+        let start = self.pos();
+        let mut end = start.clone();
+        if let Some(c) = self.peek() {
+            end.col += 1;
+            end.offset += c.len_utf8();
+        }
+        Span::new(start, end)
     }
 }
 pub trait Lexable: Sized {
     type Error;
+    fn error() -> Self;
     fn lex_next<'a>(lexer: &mut Lexer<'a>) -> Result<Option<Located<Self>>, Located<Self::Error>>;
-    fn lex(text: &str) -> Result<Vec<Located<Self>>, Located<Self::Error>> {
+    fn lex(text: &str) -> (Vec<Located<Self>>, Vec<Located<Self::Error>>) {
         let mut lexer = Lexer {
             chars: text.chars().peekable(),
             ln: 0,
             col: 0,
+            offset: 0,
         };
         let mut tokens = vec![];
-        while let Some(token) = Self::lex_next(&mut lexer)? {
-            tokens.push(token)
+        let mut errors = vec![];
+        loop {
+            match Self::lex_next(&mut lexer) {
+                Ok(Some(token)) => tokens.push(token),
+                Ok(None) => break,
+                Err(err) => {
+                    let pos = Span::new(err.pos.start.clone(), lexer.pos());
+                    tokens.push(Located::new(Self::error(), pos));
+                    errors.push(err);
+                }
+            }
         }
-        Ok(tokens)
+        (tokens, errors)
     }
 }
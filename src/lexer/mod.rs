@@ -6,6 +6,32 @@ pub mod lexer;
 pub mod position;
 pub mod tokens;
 
-pub fn lex(text: &str) -> Result<Vec<Located<Token>>, Located<LexError>> {
+pub fn lex(text: &str) -> (Vec<Located<Token>>, Vec<Located<LexError>>) {
     Token::lex(text)
 }
+
+/// whether `text` has no unclosed brackets, strings or comments left, so the REPL can stop
+/// asking for more lines
+pub fn complete(text: &str) -> bool {
+    let (tokens, errors) = lex(text);
+    if errors.iter().any(|err| match err.value {
+        // ordinary strings end at the line, only triple-quoted and raw ones continue
+        LexError::UnclosedString => {
+            let rest = &text[err.pos.start.offset..];
+            rest.starts_with("\"\"\"") || rest.starts_with("r\"")
+        }
+        LexError::UnclosedComment => true,
+        _ => false,
+    }) {
+        return false;
+    }
+    let mut depth: isize = 0;
+    for token in tokens {
+        match token.value {
+            Token::ParanLeft | Token::BracketLeft | Token::BraceLeft => depth += 1,
+            Token::ParanRight | Token::BracketRight | Token::BraceRight => depth -= 1,
+            _ => {}
+        }
+    }
+    depth <= 0
+}
@@ -4,22 +4,50 @@ use std::fmt::{Debug, Display};
 pub struct Position {
     pub ln: usize,
     pub col: usize,
+    pub offset: usize,
 }
 impl Position {
-    pub fn new(ln: usize, col: usize) -> Self {
-        Self { ln, col }
+    pub fn new(ln: usize, col: usize, offset: usize) -> Self {
+        Self { ln, col, offset }
+    }
+}
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+impl Span {
+    pub fn new(start: Position, end: Position) -> Self {
+        Self { start, end }
+    }
+    pub fn to(&self, other: &Self) -> Self {
+        Self {
+            start: self.start.clone(),
+            end: other.end.clone(),
+        }
+    }
+}
+impl From<Position> for Span {
+    fn from(value: Position) -> Self {
+        Self {
+            start: value.clone(),
+            end: value,
+        }
     }
 }
 pub struct Located<T> {
     pub value: T,
-    pub pos: Position,
+    pub pos: Span,
 }
 impl<T> Located<T> {
-    pub fn new(value: T, pos: Position) -> Self {
+    pub fn new(value: T, pos: Span) -> Self {
         Self { value, pos }
     }
     pub fn map<U, F: Fn(T) -> U>(self, f: F) -> Located<U> {
-        Located { value: f(self.value), pos: self.pos }
+        Located {
+            value: f(self.value),
+            pos: self.pos,
+        }
     }
 }
 impl<T: Debug> Debug for Located<T> {
@@ -49,7 +77,7 @@ impl<T: Default> Default for Located<T> {
     fn default() -> Self {
         Self {
             value: T::default(),
-            pos: Position::default(),
+            pos: Span::default(),
         }
     }
 }
@@ -1,8 +1,9 @@
 use super::{
     lexer::{Lexable, Lexer},
-    position::Located,
+    position::{Located, Position, Span},
 };
-use std::{fmt::Display, num::ParseFloatError};
+use crate::diagnostic::diagnostic::Diagnose;
+use std::{fmt::Display, mem, num::ParseFloatError};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
@@ -10,11 +11,15 @@ pub enum Token {
     Ident(String),
     Number(f64),
     String(String),
+    Interpolation(Vec<Interpolation>),
+    Doc(String),
+    Error,
 
     // symbols
     Equal,            // =
     Comma,            // ,
     Dot,              // .
+    Ellipsis,         // ...
     ParanLeft,        // (
     ParanRight,       // )
     BracketLeft,      // [
@@ -44,6 +49,21 @@ pub enum Token {
     If,
     Else,
     While,
+    For,
+    In,
+    Break,
+    Continue,
+    Try,
+    Catch,
+    Throw,
+    True,
+    False,
+    Null,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub enum Interpolation {
+    String(String),
+    Tokens(Vec<Located<Token>>),
 }
 impl Token {
     fn ident(ident: String) -> Self {
@@ -54,6 +74,16 @@ impl Token {
             "if" => Self::If,
             "else" => Self::Else,
             "while" => Self::While,
+            "for" => Self::For,
+            "in" => Self::In,
+            "break" => Self::Break,
+            "continue" => Self::Continue,
+            "try" => Self::Try,
+            "catch" => Self::Catch,
+            "throw" => Self::Throw,
+            "true" => Self::True,
+            "false" => Self::False,
+            "null" => Self::Null,
             _ => Self::Ident(ident),
         }
     }
@@ -61,7 +91,9 @@ impl Token {
         match self {
             Self::Ident(_) => "identifier".to_string(),
             Self::Number(_) => "number".to_string(),
-            Self::String(_) => "string".to_string(),
+            Self::String(_) | Self::Interpolation(_) => "string".to_string(),
+            Self::Doc(_) => "doc comment".to_string(),
+            Self::Error => "invalid token".to_string(),
             _ => format!("{:?}", self.to_string()),
         }
     }
@@ -72,9 +104,33 @@ impl Display for Token {
             Self::Ident(ident) => write!(f, "{ident}"),
             Self::Number(number) => write!(f, "{number}"),
             Self::String(string) => write!(f, "{string:?}"),
+            Self::Interpolation(parts) => {
+                write!(f, "\"")?;
+                for part in parts {
+                    match part {
+                        Interpolation::String(string) => {
+                            write!(f, "{}", string.escape_debug())?;
+                        }
+                        Interpolation::Tokens(tokens) => {
+                            write!(f, "{{")?;
+                            for (i, token) in tokens.iter().enumerate() {
+                                if i > 0 {
+                                    write!(f, " ")?;
+                                }
+                                write!(f, "{token}")?;
+                            }
+                            write!(f, "}}")?;
+                        }
+                    }
+                }
+                write!(f, "\"")
+            }
+            Self::Doc(doc) => write!(f, "///{doc}"),
+            Self::Error => write!(f, "<error>"),
             Self::Equal => write!(f, "="),
             Self::Comma => write!(f, ","),
             Self::Dot => write!(f, "."),
+            Self::Ellipsis => write!(f, "..."),
             Self::ParanLeft => write!(f, "("),
             Self::ParanRight => write!(f, ")"),
             Self::BracketLeft => write!(f, "["),
@@ -102,6 +158,16 @@ impl Display for Token {
             Self::If => write!(f, "if"),
             Self::Else => write!(f, "else"),
             Self::While => write!(f, "while"),
+            Self::For => write!(f, "for"),
+            Self::In => write!(f, "in"),
+            Self::Break => write!(f, "break"),
+            Self::Continue => write!(f, "continue"),
+            Self::Try => write!(f, "try"),
+            Self::Catch => write!(f, "catch"),
+            Self::Throw => write!(f, "throw"),
+            Self::True => write!(f, "true"),
+            Self::False => write!(f, "false"),
+            Self::Null => write!(f, "null"),
         }
     }
 }
@@ -111,70 +177,157 @@ pub enum LexError {
     BadCharacter(char),
     ParseNumberError(ParseFloatError),
     UnclosedString,
+    UnclosedComment,
+    InvalidEscape(String),
+    InvalidDigit { digit: char, radix: u32 },
+    MissingDigits { radix: u32 },
+    EmptyExponent,
 }
 impl Lexable for Token {
     type Error = LexError;
-    fn lex_next<'a>(lexer: &mut Lexer<'a>) -> Result<Option<Located<Self>>, Located<Self::Error>> {
-        while let Some(c) = lexer.peek() {
-            if !c.is_ascii_whitespace() {
+    fn error() -> Self {
+        Self::Error
+    }
+    fn lex_next<'a>(lexer: &mut Lexer<'a>) -> Result<Option<Located<Self>>, Located<LexError>> {
+        loop {
+            while let Some(c) = lexer.peek() {
+                if !c.is_ascii_whitespace() {
+                    break;
+                }
+                lexer.next();
+            }
+            if lexer.peek() != Some(&'/') {
                 break;
             }
-            lexer.next();
+            match lexer.peek_nth(1) {
+                Some('/') => {
+                    if lexer.peek_nth(2) == Some('/')
+                        && lexer.peek_nth(3) != Some('/')
+                        && documents_def(lexer)
+                    {
+                        break;
+                    }
+                    while let Some(c) = lexer.peek() {
+                        if *c == '\n' {
+                            break;
+                        }
+                        lexer.next();
+                    }
+                }
+                Some('*') => {
+                    let start = lexer.pos();
+                    lexer.next();
+                    lexer.next();
+                    let pos = Span::new(start, lexer.pos());
+                    let mut depth = 1;
+                    while depth > 0 {
+                        match (lexer.next(), lexer.peek()) {
+                            (Some('/'), Some('*')) => {
+                                lexer.next();
+                                depth += 1;
+                            }
+                            (Some('*'), Some('/')) => {
+                                lexer.next();
+                                depth -= 1;
+                            }
+                            (Some(_), _) => {}
+                            (None, _) => return Err(Located::new(LexError::UnclosedComment, pos)),
+                        }
+                    }
+                }
+                _ => break,
+            }
         }
         let pos = lexer.pos();
         let Some(c) = lexer.next() else {
             return Ok(None);
         };
-        match c {
+        let token = match c {
             '=' => {
                 if lexer.peek() == Some(&'=') {
                     lexer.next();
-                    Ok(Some(Located::new(Token::EqualEqual, pos)))
+                    Token::EqualEqual
                 } else {
-                    Ok(Some(Located::new(Token::Equal, pos)))
-                }
-            }
-            ',' => Ok(Some(Located::new(Token::Comma, pos))),
-            '.' => Ok(Some(Located::new(Token::Dot, pos))),
-            '(' => Ok(Some(Located::new(Token::ParanLeft, pos))),
-            ')' => Ok(Some(Located::new(Token::ParanRight, pos))),
-            '[' => Ok(Some(Located::new(Token::BracketLeft, pos))),
-            ']' => Ok(Some(Located::new(Token::BracketRight, pos))),
-            '{' => Ok(Some(Located::new(Token::BraceLeft, pos))),
-            '}' => Ok(Some(Located::new(Token::BraceRight, pos))),
-            '+' => Ok(Some(Located::new(Token::Plus, pos))),
-            '-' => Ok(Some(Located::new(Token::Minus, pos))),
-            '*' => Ok(Some(Located::new(Token::Star, pos))),
-            '/' => Ok(Some(Located::new(Token::Slash, pos))),
-            '%' => Ok(Some(Located::new(Token::Percent, pos))),
-            '^' => Ok(Some(Located::new(Token::Exponent, pos))),
+                    Token::Equal
+                }
+            }
+            ',' => Token::Comma,
+            '.' if lexer.peek().is_some_and(|c| c.is_ascii_digit()) => {
+                Token::Number(Self::number(lexer, c, &pos)?)
+            }
+            '.' if lexer.peek() == Some(&'.') && lexer.peek_nth(1) == Some('.') => {
+                lexer.next();
+                lexer.next();
+                Token::Ellipsis
+            }
+            '.' => Token::Dot,
+            '(' => Token::ParanLeft,
+            ')' => Token::ParanRight,
+            '[' => Token::BracketLeft,
+            ']' => Token::BracketRight,
+            '{' => Token::BraceLeft,
+            '}' => Token::BraceRight,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '/' => {
+                if lexer.peek() == Some(&'/') {
+                    lexer.next();
+                    lexer.next();
+                    if lexer.peek() == Some(&' ') {
+                        lexer.next();
+                    }
+                    let mut doc = String::new();
+                    while let Some(c) = lexer.peek() {
+                        if *c == '\n' {
+                            break;
+                        }
+                        doc.push(lexer.next().unwrap())
+                    }
+                    Token::Doc(doc)
+                } else {
+                    Token::Slash
+                }
+            }
+            '%' => Token::Percent,
+            '^' => Token::Exponent,
             '<' => {
                 if lexer.peek() == Some(&'=') {
                     lexer.next();
-                    Ok(Some(Located::new(Token::LessEqual, pos)))
+                    Token::LessEqual
                 } else {
-                    Ok(Some(Located::new(Token::Less, pos)))
+                    Token::Less
                 }
             }
             '>' => {
                 if lexer.peek() == Some(&'=') {
                     lexer.next();
-                    Ok(Some(Located::new(Token::GreaterEqual, pos)))
+                    Token::GreaterEqual
                 } else {
-                    Ok(Some(Located::new(Token::Greater, pos)))
+                    Token::Greater
                 }
             }
-            '&' => Ok(Some(Located::new(Token::Ampersand, pos))),
-            '|' => Ok(Some(Located::new(Token::Pipe, pos))),
+            '&' => Token::Ampersand,
+            '|' => Token::Pipe,
             '!' => {
                 if lexer.peek() == Some(&'=') {
                     lexer.next();
-                    Ok(Some(Located::new(Token::ExclamationEqual, pos)))
+                    Token::ExclamationEqual
                 } else {
-                    Ok(Some(Located::new(Token::Exclamation, pos)))
+                    Token::Exclamation
                 }
             }
             '"' => {
+                let triple = lexer.peek() == Some(&'"') && lexer.peek_nth(1) == Some('"');
+                if triple {
+                    lexer.next();
+                    lexer.next();
+                }
+                Self::string(lexer, &Span::new(pos.clone(), lexer.pos()), triple)?
+            }
+            'r' if lexer.peek() == Some(&'"') => {
+                lexer.next();
+                let open = Span::new(pos.clone(), lexer.pos());
                 let mut string = String::new();
                 while let Some(c) = lexer.peek() {
                     if *c == '"' {
@@ -183,48 +336,281 @@ impl Lexable for Token {
                     string.push(lexer.next().unwrap())
                 }
                 if lexer.next() != Some('"') {
-                    return Err(Located::new(LexError::UnclosedString, pos));
+                    return Err(Located::new(LexError::UnclosedString, open));
                 }
-                Ok(Some(Located::new(Token::String(string), pos)))
+                Token::String(string)
             }
-            c if c.is_ascii_digit() => {
-                let mut number = String::from(c);
+            c if c.is_ascii_digit() => Token::Number(Self::number(lexer, c, &pos)?),
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut ident = String::from(c);
                 while let Some(c) = lexer.peek() {
-                    if !c.is_ascii_digit() {
+                    if !c.is_alphanumeric() && *c != '_' {
                         break;
                     }
-                    number.push(lexer.next().unwrap())
+                    ident.push(lexer.next().unwrap())
                 }
-                if lexer.peek() == Some(&'.') {
-                    number.push(lexer.next().unwrap());
-                    while let Some(c) = lexer.peek() {
-                        if !c.is_ascii_digit() {
-                            break;
+                Token::ident(ident)
+            }
+            c => {
+                return Err(Located::new(
+                    LexError::BadCharacter(c),
+                    Span::new(pos, lexer.pos()),
+                ))
+            }
+        };
+        Ok(Some(Located::new(token, Span::new(pos, lexer.pos()))))
+    }
+}
+/// whether the doc comment starting at the lexer is followed by a `def`, other doc comments
+/// document nothing and are skipped like ordinary comments
+fn documents_def(lexer: &Lexer) -> bool {
+    let mut chars = lexer.chars.clone();
+    chars.find(|c| *c == '\n');
+    loop {
+        let mut line = String::new();
+        let mut last = true;
+        for c in chars.by_ref() {
+            if c == '\n' {
+                last = false;
+                break;
+            }
+            line.push(c);
+        }
+        let line = line.trim_start();
+        if line.is_empty() || (line.starts_with("///") && !line.starts_with("////")) {
+            if last {
+                return false;
+            }
+            continue;
+        }
+        return line
+            .strip_prefix("def")
+            .is_some_and(|rest| !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_'));
+    }
+}
+impl Token {
+    fn string(lexer: &mut Lexer, pos: &Span, triple: bool) -> Result<Self, Located<LexError>> {
+        let mut parts = vec![];
+        let mut string = String::new();
+        let mut error = None;
+        loop {
+            let Some(c) = lexer.peek().copied() else {
+                return Err(Located::new(LexError::UnclosedString, pos.clone()));
+            };
+            // only triple-quoted strings span lines, stop here so the next line is lexed
+            if c == '\n' && !triple {
+                return Err(Located::new(
+                    LexError::UnclosedString,
+                    Span::new(pos.start.clone(), lexer.pos()),
+                ));
+            }
+            if c == '"' {
+                if !triple {
+                    lexer.next();
+                    break;
+                }
+                if lexer.peek_nth(1) == Some('"') && lexer.peek_nth(2) == Some('"') {
+                    lexer.next();
+                    lexer.next();
+                    lexer.next();
+                    break;
+                }
+            }
+            let escape_pos = lexer.pos();
+            lexer.next();
+            match c {
+                // leave the newline for the check above
+                '\\' if !triple && lexer.peek() == Some(&'\n') => {}
+                '\\' => match Self::escape(lexer, escape_pos) {
+                    Ok(c) => string.push(c),
+                    Err(err) => {
+                        error.get_or_insert(err);
+                    }
+                },
+                '{' => {
+                    if !string.is_empty() {
+                        parts.push(Interpolation::String(mem::take(&mut string)));
+                    }
+                    let mut tokens = vec![];
+                    let mut depth: usize = 0;
+                    loop {
+                        let line_end = (0..)
+                            .map(|n| lexer.peek_nth(n))
+                            .find(|c| !matches!(c, Some(' ' | '\t')))
+                            == Some(Some('\n'));
+                        if line_end && !triple {
+                            return Err(Located::new(
+                                LexError::UnclosedString,
+                                Span::new(pos.start.clone(), lexer.pos()),
+                            ));
                         }
-                        number.push(lexer.next().unwrap())
+                        let token = match Self::lex_next(lexer) {
+                            Ok(Some(token)) => token,
+                            Ok(None) => {
+                                return Err(Located::new(LexError::UnclosedString, pos.clone()))
+                            }
+                            Err(err) => {
+                                error.get_or_insert(err);
+                                continue;
+                            }
+                        };
+                        match token.value {
+                            Token::BraceLeft => depth += 1,
+                            Token::BraceRight if depth == 0 => break,
+                            Token::BraceRight => depth -= 1,
+                            _ => {}
+                        }
+                        tokens.push(token);
                     }
+                    parts.push(Interpolation::Tokens(tokens));
                 }
-                Ok(Some(Located::new(
-                    Token::Number(
-                        number
-                            .parse()
-                            .map_err(LexError::ParseNumberError)
-                            .map_err(|err| Located::new(err, pos.clone()))?,
-                    ),
+                c => string.push(c),
+            }
+        }
+        if let Some(err) = error {
+            return Err(err);
+        }
+        if parts.is_empty() {
+            return Ok(Token::String(string));
+        }
+        if !string.is_empty() {
+            parts.push(Interpolation::String(string));
+        }
+        Ok(Token::Interpolation(parts))
+    }
+    fn number(lexer: &mut Lexer, c: char, pos: &Position) -> Result<f64, Located<LexError>> {
+        let radix = match (c, lexer.peek()) {
+            ('0', Some('x' | 'X')) => 16,
+            ('0', Some('o' | 'O')) => 8,
+            ('0', Some('b' | 'B')) => 2,
+            _ => 10,
+        };
+        if radix != 10 {
+            lexer.next();
+            let mut number = 0.;
+            let mut digits = 0;
+            while let Some(c) = lexer.peek().copied() {
+                if c == '_' {
+                    lexer.next();
+                    continue;
+                }
+                if !c.is_alphanumeric() {
+                    break;
+                }
+                let Some(digit) = c.to_digit(radix) else {
+                    let pos = lexer.char_span();
+                    Self::skip_word(lexer);
+                    return Err(Located::new(
+                        LexError::InvalidDigit { digit: c, radix },
+                        pos,
+                    ));
+                };
+                lexer.next();
+                number = number * radix as f64 + digit as f64;
+                digits += 1;
+            }
+            if digits == 0 {
+                return Err(Located::new(
+                    LexError::MissingDigits { radix },
+                    Span::new(pos.clone(), lexer.pos()),
+                ));
+            }
+            return Ok(number);
+        }
+        let mut number = String::from(c);
+        Self::digits(lexer, &mut number);
+        if c != '.' && lexer.peek() == Some(&'.') {
+            number.push(lexer.next().unwrap());
+            Self::digits(lexer, &mut number);
+        }
+        if let Some('e' | 'E') = lexer.peek() {
+            number.push(lexer.next().unwrap());
+            if let Some('+' | '-') = lexer.peek() {
+                number.push(lexer.next().unwrap());
+            }
+            let len = number.len();
+            Self::digits(lexer, &mut number);
+            if number.len() == len {
+                return Err(Located::new(
+                    LexError::EmptyExponent,
+                    Span::new(pos.clone(), lexer.pos()),
+                ));
+            }
+        }
+        if let Some(c) = lexer.peek().copied() {
+            if c.is_alphanumeric() {
+                let pos = lexer.char_span();
+                Self::skip_word(lexer);
+                return Err(Located::new(
+                    LexError::InvalidDigit { digit: c, radix },
                     pos,
-                )))
+                ));
             }
-            c if c.is_alphanumeric() || c == '_' => {
-                let mut ident = String::from(c);
-                while let Some(c) = lexer.peek() {
-                    if !c.is_alphanumeric() && *c != '_' {
-                        break;
+        }
+        number
+            .parse()
+            .map_err(LexError::ParseNumberError)
+            .map_err(|err| Located::new(err, Span::new(pos.clone(), lexer.pos())))
+    }
+    fn skip_word(lexer: &mut Lexer) {
+        while lexer
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || *c == '_')
+        {
+            lexer.next();
+        }
+    }
+    fn digits(lexer: &mut Lexer, number: &mut String) {
+        while let Some(c) = lexer.peek().copied() {
+            if c == '_' {
+                lexer.next();
+                continue;
+            }
+            if !c.is_ascii_digit() {
+                break;
+            }
+            number.push(lexer.next().unwrap())
+        }
+    }
+    fn escape(lexer: &mut Lexer, pos: Position) -> Result<char, Located<LexError>> {
+        let Some(c) = lexer.next() else {
+            return Err(Located::new(LexError::UnclosedString, Span::from(pos)));
+        };
+        match c {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            '0' => Ok('\0'),
+            '\\' | '"' | '\'' | '{' | '}' => Ok(c),
+            'u' => {
+                let mut escape = String::from(c);
+                if lexer.peek() == Some(&'{') {
+                    escape.push(lexer.next().unwrap());
+                    while let Some(c) = lexer.peek() {
+                        if !c.is_ascii_hexdigit() {
+                            break;
+                        }
+                        escape.push(lexer.next().unwrap())
+                    }
+                    if lexer.peek() == Some(&'}') {
+                        escape.push(lexer.next().unwrap());
+                        if let Some(c) = u32::from_str_radix(&escape[2..escape.len() - 1], 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                        {
+                            return Ok(c);
+                        }
                     }
-                    ident.push(lexer.next().unwrap())
                 }
-                Ok(Some(Located::new(Token::ident(ident), pos)))
+                Err(Located::new(
+                    LexError::InvalidEscape(escape),
+                    Span::new(pos, lexer.pos()),
+                ))
             }
-            c => Err(Located::new(LexError::BadCharacter(c), pos)),
+            c => Err(Located::new(
+                LexError::InvalidEscape(c.to_string()),
+                Span::new(pos, lexer.pos()),
+            )),
         }
     }
 }
@@ -234,6 +620,42 @@ impl Display for LexError {
             LexError::BadCharacter(c) => write!(f, "bad character {c:?}"),
             LexError::ParseNumberError(err) => write!(f, "error while parsing number: {err}"),
             LexError::UnclosedString => write!(f, "unclosed string"),
+            LexError::UnclosedComment => write!(f, "unclosed block comment"),
+            LexError::InvalidEscape(escape) => write!(f, "invalid escape sequence \\{escape}"),
+            LexError::InvalidDigit { digit, radix } => {
+                write!(f, "invalid digit {digit:?} for a base {radix} number")
+            }
+            LexError::MissingDigits { radix } => {
+                write!(f, "expected at least one digit for a base {radix} number")
+            }
+            LexError::EmptyExponent => write!(f, "expected at least one digit in exponent"),
+        }
+    }
+}
+impl Diagnose for LexError {
+    fn label(&self) -> Option<String> {
+        match self {
+            LexError::UnclosedString => Some("string starts here".into()),
+            LexError::UnclosedComment => Some("comment starts here".into()),
+            LexError::InvalidDigit { .. } => Some("invalid digit".into()),
+            _ => None,
+        }
+    }
+    fn notes(&self) -> Vec<String> {
+        match self {
+            LexError::UnclosedComment => vec!["block comments nest".into()],
+            LexError::InvalidEscape(_) => {
+                vec![r#"valid escapes are \n \t \r \0 \\ \" \' \{ \} and \u{...}"#.into()]
+            }
+            _ => vec![],
+        }
+    }
+    fn help(&self) -> Option<String> {
+        match self {
+            LexError::UnclosedString => Some("add a closing `\"`".into()),
+            LexError::UnclosedComment => Some("add a closing `*/`".into()),
+            LexError::EmptyExponent => Some("add digits after the exponent, e.g. `1e9`".into()),
+            _ => None,
         }
     }
 }
@@ -0,0 +1,7 @@
+#![allow(clippy::module_inception)]
+pub mod compiler;
+pub mod diagnostic;
+pub mod engine;
+pub mod interpreter;
+pub mod lexer;
+pub mod parser;
@@ -1,52 +1,190 @@
-use crate::{interpreter::run, lexer::lex, parser::parse};
-use compiler::compile;
-use lexer::position::Located;
-use std::{env, fs, process::exit, rc::Rc};
+use compiler::{
+    compiler::{
+        bytecode::Closure,
+        compiler::{Compilable, Compiler},
+        serialize::{deserialize, serialize, MAGIC},
+    },
+    diagnostic::diagnostic::{Diagnose, Diagnostic},
+    interpreter::{interpreter::TracedError, run, value::Value},
+    lexer::lex,
+    lexer::position::Located,
+    parser::parse,
+};
+use repl::repl;
+use std::{
+    env, fs,
+    io::{self, IsTerminal},
+    path::Path,
+    process::exit,
+    rc::Rc,
+};
 
-pub mod compiler;
-pub mod interpreter;
-pub mod lexer;
-pub mod parser;
+mod repl;
 
-fn main() {
-    let mut args = env::args().skip(1);
-    if let Some(path) = args.next() {
-        let text = fs::read_to_string(&path)
+const USAGE: &str = "\
+usage: compiler [options] [file]
+       compiler [options] run <file>
+       compiler [options] build <file> [-o <output>]
+
+options:
+    --strict          forbid assignments to undeclared variables
+    --dump-tokens     print the tokens of the source file
+    --dump-ast        print the syntax tree of the source file
+    --dump-bytecode   print the compiled bytecode";
+
+#[derive(Debug, Default)]
+struct Options {
+    strict: bool,
+    dump_tokens: bool,
+    dump_ast: bool,
+    dump_bytecode: bool,
+}
+
+fn report<E: Diagnose>(path: &str, text: &str, errors: &[Located<E>]) {
+    let color = io::stderr().is_terminal();
+    for err in errors {
+        eprintln!("{}", Diagnostic::from(err).render(path, text, color));
+    }
+}
+
+/// reports a runtime error followed by its stack trace, innermost call first
+fn report_traced(path: &str, text: &str, err: TracedError) {
+    let path = err.trace.first().map_or(path, |frame| frame.path.as_str());
+    report(path, text, &[err.err]);
+    if !err.trace.is_empty() {
+        eprintln!("stack trace (innermost call first):");
+        for frame in &err.trace {
+            eprintln!("    at {frame}");
+        }
+    }
+}
+
+fn compile(path: &str, text: &str, options: &Options) -> Closure {
+    let (tokens, lex_errors) = lex(text);
+    if options.dump_tokens {
+        for Located { value: token, pos } in &tokens {
+            let location = format!("{}:{}", pos.start.ln + 1, pos.start.col + 1);
+            println!("{location:<8}{token}");
+        }
+    }
+    let (chunk, parse_errors) = parse(tokens);
+    if options.dump_ast {
+        println!("{chunk:#?}");
+    }
+    if !lex_errors.is_empty() || !parse_errors.is_empty() {
+        report(path, text, &lex_errors);
+        report(path, text, &parse_errors);
+        exit(1);
+    }
+    let mut compiler = Compiler {
+        strict: options.strict,
+        path: path.to_string(),
+        ..Default::default()
+    };
+    let closure = chunk.compile(&mut compiler).unwrap_or_else(|err| {
+        report(path, text, &[err]);
+        exit(1);
+    });
+    if options.dump_bytecode {
+        print!("{closure}");
+    }
+    closure
+}
+
+fn read(path: &str) -> Vec<u8> {
+    fs::read(path)
+        .map_err(|err| {
+            eprintln!("ERROR {path}: {err}");
+            exit(1);
+        })
+        .unwrap()
+}
+
+fn source(path: &str, bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes)
+        .map_err(|err| {
+            eprintln!("ERROR {path}: {err}");
+            exit(1);
+        })
+        .unwrap()
+}
+
+fn build(path: &str, output: Option<String>, options: &Options) {
+    let text = source(path, read(path));
+    let closure = compile(path, &text, options);
+    let output = output.unwrap_or_else(|| {
+        Path::new(path)
+            .with_extension("bc")
+            .to_string_lossy()
+            .into_owned()
+    });
+    fs::write(&output, serialize(&closure))
+        .map_err(|err| {
+            eprintln!("ERROR {output}: {err}");
+            exit(1);
+        })
+        .unwrap();
+}
+
+/// runs either a source file or, if it starts with the bytecode header, a compiled one
+fn execute(path: &str, options: &Options) {
+    let bytes = read(path);
+    let (text, closure) = if bytes.starts_with(MAGIC) {
+        let closure = deserialize(&bytes)
             .map_err(|err| {
                 eprintln!("ERROR {path}: {err}");
                 exit(1);
             })
             .unwrap();
-        let tokens = lex(&text)
-            .map_err(|Located { value: err, pos }| {
-                eprintln!("ERROR {path}:{}:{}: {err}", pos.ln + 1, pos.col + 1);
-                exit(1);
-            })
-            .unwrap();
-        let chunk = parse(tokens)
-            .map_err(|Located { value: err, pos }| {
-                eprintln!("ERROR {path}:{}:{}: {err}", pos.ln + 1, pos.col + 1);
-                exit(1);
-            })
-            .unwrap();
-        let closure = compile(chunk)
-            .map_err(|Located { value: _, pos }| {
-                eprintln!("ERROR {path}:{}:{}: compiler error", pos.ln + 1, pos.col + 1);
-                exit(1);
-            })
-            .unwrap();
-        // dbg!(&closure);
-        let value = run(&Rc::new(closure))
-            .map_err(|Located { value: err, pos }| {
-                eprintln!("ERROR {path}:{}:{}: {err}", pos.ln + 1, pos.col + 1);
-                exit(1);
-            })
-            .unwrap();
-        if let Some(value) = value {
-            println!("{value}");
+        if options.dump_bytecode {
+            print!("{closure}");
         }
+        (String::new(), closure)
     } else {
-        eprintln!("ERROR: no input file path provided");
+        let text = source(path, bytes);
+        let closure = compile(path, &text, options);
+        (text, closure)
+    };
+    let value = run(&Rc::new(closure)).unwrap_or_else(|err| {
+        report_traced(path, &text, err);
         exit(1);
+    });
+    if let Some(value) = value.filter(|value| value != &Value::Null) {
+        println!("{value}");
+    }
+}
+
+fn main() {
+    let mut options = Options::default();
+    let mut output = None;
+    let mut positional = vec![];
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--strict" => options.strict = true,
+            "--dump-tokens" => options.dump_tokens = true,
+            "--dump-ast" => options.dump_ast = true,
+            "--dump-bytecode" => options.dump_bytecode = true,
+            "-o" => output = args.next(),
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return;
+            }
+            _ => positional.push(arg),
+        }
+    }
+    match positional
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [] => repl(),
+        ["build", path] => build(path, output, &options),
+        ["run", path] | [path] => execute(path, &options),
+        _ => {
+            eprintln!("{USAGE}");
+            exit(1);
+        }
     }
 }
@@ -1,7 +1,10 @@
 use super::parser::{Parsable, Parser};
-use crate::lexer::{
-    position::{Located, Position},
-    tokens::Token,
+use crate::{
+    diagnostic::diagnostic::Diagnose,
+    lexer::{
+        position::{Located, Span},
+        tokens::{Interpolation, Token},
+    },
 };
 use std::fmt::Display;
 
@@ -17,18 +20,17 @@ pub enum Statement {
         expr: Located<Expression>,
     },
     Assign {
-        ident: Located<String>,
+        target: Located<Expression>,
         expr: Located<Expression>,
     },
-    Call {
-        ident: Located<String>,
-        args: Vec<Located<Expression>>,
-    },
+    Expression(Located<Expression>),
 
     Def {
         ident: Located<String>,
-        params: Vec<Located<String>>,
+        params: Vec<Parameter>,
+        rest: Option<Located<String>>,
         body: Located<Block>,
+        doc: Option<String>,
     },
     If {
         cond: Located<Expression>,
@@ -39,8 +41,26 @@ pub enum Statement {
         cond: Located<Expression>,
         body: Located<Block>,
     },
+    For {
+        ident: Located<String>,
+        iter: Located<Expression>,
+        body: Located<Block>,
+    },
+    Try {
+        body: Located<Block>,
+        ident: Located<String>,
+        handler: Located<Block>,
+    },
 
     Return(Located<Expression>),
+    Throw(Located<Expression>),
+    Break,
+    Continue,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub ident: Located<String>,
+    pub default: Option<Located<Expression>>,
 }
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
@@ -58,6 +78,14 @@ pub enum Expression {
         head: Box<Located<Self>>,
         args: Vec<Located<Self>>,
     },
+    Index {
+        head: Box<Located<Self>>,
+        index: Box<Located<Self>>,
+    },
+    Field {
+        head: Box<Located<Self>>,
+        field: Located<String>,
+    },
 }
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum BinaryOperator {
@@ -80,31 +108,37 @@ pub enum BinaryOperator {
 pub enum UnaryOperator {
     Minus,       // -
     Exclamation, // !
+    /// not written in source, converts the parts of an interpolated string
+    ToString,
 }
 #[derive(Debug, Clone, PartialEq)]
 pub enum Atom {
     Ident(String),
     Number(f64),
     String(String),
+    Boolean(bool),
+    Null,
+    List(Vec<Located<Expression>>),
+    Map(Vec<(Located<String>, Located<Expression>)>),
     Expression(Box<Located<Expression>>),
 }
 
 macro_rules! expected {
     ($parser:ident) => {{
         let Some(token) = $parser.next() else {
-            return Err(Located::new(ParseError::UnexpectedEOF, Position::default()));
+            return Err(Located::new(ParseError::UnexpectedEOF, $parser.eof.clone()));
         };
         token
     }};
     ($parser:ident : &) => {{
         let Some(token) = $parser.peek() else {
-            return Err(Located::new(ParseError::UnexpectedEOF, Position::default()));
+            return Err(Located::new(ParseError::UnexpectedEOF, $parser.eof.clone()));
         };
         token
     }};
     ($parser:ident : $token:ident) => {{
         let Some(Located { value: token, pos }) = $parser.next() else {
-            return Err(Located::new(ParseError::UnexpectedEOF, Position::default()));
+            return Err(Located::new(ParseError::UnexpectedEOF, $parser.eof.clone()));
         };
         if token != Token::$token {
             return Err(Located::new(
@@ -123,6 +157,7 @@ pub enum ParseError {
     UnexpectedEOF,
     UnexpectedToken(Token),
     ExpectedToken { expected: Token, got: Token },
+    RequiredAfterDefault(String),
 }
 
 impl Parsable for Chunk {
@@ -130,9 +165,17 @@ impl Parsable for Chunk {
     fn parse(parser: &mut Parser) -> Result<Located<Self>, Located<Self::Error>> {
         let mut stats = vec![];
         while parser.peek().is_some() {
-            stats.push(Statement::parse(parser)?);
+            let start = parser.consumed();
+            match Statement::parse(parser) {
+                Ok(stat) => stats.push(stat),
+                Err(err) => parser.recover(err, start),
+            }
         }
-        Ok(Located::new(Self(stats), Position::default()))
+        let pos = match (stats.first(), stats.last()) {
+            (Some(first), Some(last)) => first.pos.to(&last.pos),
+            _ => Span::default(),
+        };
+        Ok(Located::new(Self(stats), pos))
     }
 }
 impl Parsable for Block {
@@ -148,29 +191,36 @@ impl Parsable for Block {
             if token == &Token::BraceRight {
                 break;
             }
-            stats.push(Statement::parse(parser)?);
+            let start = parser.consumed();
+            match Statement::parse(parser) {
+                Ok(stat) => stats.push(stat),
+                Err(err) => parser.recover(err, start),
+            }
         }
-        expected!(parser: BraceRight);
-        Ok(Located::new(Self(stats), pos))
+        let Located { value: _, pos: end } = expected!(parser: BraceRight);
+        Ok(Located::new(Self(stats), pos.to(&end)))
     }
 }
 impl Parsable for Statement {
     type Error = ParseError;
     fn parse(parser: &mut Parser) -> Result<Located<Self>, Located<Self::Error>> {
-        let Located { value: token, pos } = expected!(parser:&);
+        let doc = Self::doc(parser);
+        let Located { value: token, pos: _ } = expected!(parser:&);
         match token {
             Token::Let => {
                 let Located { value: _, pos } = expected!(parser);
                 let ident = Atom::ident(parser)?;
                 expected!(parser: Equal);
                 let expr = Expression::parse(parser)?;
+                let pos = pos.to(&expr.pos);
                 Ok(Located::new(Self::Let { ident, expr }, pos))
             }
             Token::Def => {
                 let Located { value: _, pos } = expected!(parser);
                 let ident = Atom::ident(parser)?;
                 expected!(parser: ParanLeft);
-                let mut params = vec![];
+                let mut params: Vec<Parameter> = vec![];
+                let mut rest = None;
                 while let Some(Located {
                     value: token,
                     pos: _,
@@ -179,7 +229,30 @@ impl Parsable for Statement {
                     if token == &Token::ParanRight {
                         break;
                     }
-                    params.push(Atom::ident(parser)?);
+                    if token == &Token::Ellipsis {
+                        parser.next();
+                        rest = Some(Atom::ident(parser)?);
+                        break;
+                    }
+                    let ident = Atom::ident(parser)?;
+                    let default = if let Some(Located {
+                        value: Token::Equal,
+                        pos: _,
+                    }) = parser.peek()
+                    {
+                        parser.next();
+                        Some(Expression::parse(parser)?)
+                    } else {
+                        if params.last().is_some_and(|param| param.default.is_some()) {
+                            // keep parsing the definition, the rest of it is still valid
+                            parser.errors.push(Located::new(
+                                ParseError::RequiredAfterDefault(ident.value.clone()),
+                                ident.pos.clone(),
+                            ));
+                        }
+                        None
+                    };
+                    params.push(Parameter { ident, default });
                     if let Some(Located {
                         value: Token::ParanRight,
                         pos: _,
@@ -190,11 +263,22 @@ impl Parsable for Statement {
                 }
                 expected!(parser: ParanRight);
                 let body = Block::parse(parser)?;
-                Ok(Located::new(Self::Def { ident, params, body }, pos))
+                let pos = pos.to(&body.pos);
+                Ok(Located::new(
+                    Self::Def {
+                        ident,
+                        params,
+                        rest,
+                        body,
+                        doc,
+                    },
+                    pos,
+                ))
             }
             Token::Return => {
                 let Located { value: _, pos } = expected!(parser);
                 let expr = Expression::parse(parser)?;
+                let pos = pos.to(&expr.pos);
                 Ok(Located::new(Self::Return(expr), pos))
             }
             Token::If => {
@@ -212,55 +296,99 @@ impl Parsable for Statement {
                         Block::parse(parser)?
                     });
                 }
+                let pos = pos.to(&else_case.as_ref().unwrap_or(&case).pos);
                 Ok(Located::new(Self::If { cond, case, else_case }, pos))
             }
             Token::While => {
                 let Located { value: _, pos } = expected!(parser);
                 let cond = Expression::parse(parser)?;
                 let body = Block::parse(parser)?;
+                let pos = pos.to(&body.pos);
                 Ok(Located::new(Self::While { cond, body }, pos))
             }
-            
-            Token::Ident(_) => {
+            Token::For => {
+                let Located { value: _, pos } = expected!(parser);
                 let ident = Atom::ident(parser)?;
-                let Located { value: token, pos } = expected!(parser);
-                match token {
-                    Token::Equal => {
-                        let expr = Expression::parse(parser)?;
-                        Ok(Located::new(Self::Assign { ident, expr }, pos))
-                    }
-                    Token::ParanLeft => {
-                        let mut args = vec![];
-                        while let Some(Located {
-                            value: token,
-                            pos: _,
-                        }) = parser.peek()
-                        {
-                            if token == &Token::ParanRight {
-                                break;
-                            }
-                            args.push(Expression::parse(parser)?);
-                            if let Some(Located {
-                                value: Token::ParanRight,
-                                pos: _,
-                            }) = parser.peek() {
-                                break;
-                            }
-                            expected!(parser: Comma);
-                        }
-                        expected!(parser: ParanRight);
-                        Ok(Located::new(Self::Call { ident, args }, pos))
-                    }
-                    token => Err(Located::new(ParseError::UnexpectedToken(token), pos)),
+                expected!(parser: In);
+                let iter = Expression::parse(parser)?;
+                let body = Block::parse(parser)?;
+                let pos = pos.to(&body.pos);
+                Ok(Located::new(Self::For { ident, iter, body }, pos))
+            }
+            Token::Try => {
+                let Located { value: _, pos } = expected!(parser);
+                let body = Block::parse(parser)?;
+                expected!(parser: Catch);
+                let ident = Atom::ident(parser)?;
+                let handler = Block::parse(parser)?;
+                let pos = pos.to(&handler.pos);
+                Ok(Located::new(
+                    Self::Try {
+                        body,
+                        ident,
+                        handler,
+                    },
+                    pos,
+                ))
+            }
+            Token::Throw => {
+                let Located { value: _, pos } = expected!(parser);
+                let expr = Expression::parse(parser)?;
+                let pos = pos.to(&expr.pos);
+                Ok(Located::new(Self::Throw(expr), pos))
+            }
+            Token::Break => {
+                let Located { value: _, pos } = expected!(parser);
+                Ok(Located::new(Self::Break, pos))
+            }
+            Token::Continue => {
+                let Located { value: _, pos } = expected!(parser);
+                Ok(Located::new(Self::Continue, pos))
+            }
+            _ => {
+                let head = Expression::parse(parser)?;
+                if let Some(Located {
+                    value: Token::Equal,
+                    pos: _,
+                }) = parser.peek()
+                {
+                    parser.next();
+                    let expr = Expression::parse(parser)?;
+                    let pos = head.pos.to(&expr.pos);
+                    return Ok(Located::new(Self::Assign { target: head, expr }, pos));
                 }
+                let pos = head.pos.clone();
+                Ok(Located::new(Self::Expression(head), pos))
             }
-            _ => Err(Located::new(
-                ParseError::UnexpectedToken(token.clone()),
-                pos.clone(),
-            )),
         }
     }
 }
+impl Statement {
+    /// the doc comment lines before a `def`, the lexer only keeps those
+    pub fn doc(parser: &mut Parser) -> Option<String> {
+        let mut doc: Option<String> = None;
+        while let Some(Located {
+            value: Token::Doc(_),
+            pos: _,
+        }) = parser.peek()
+        {
+            let Some(Located {
+                value: Token::Doc(line),
+                pos: _,
+            }) = parser.next()
+            else {
+                unreachable!()
+            };
+            if let Some(doc) = &mut doc {
+                doc.push('\n');
+                doc.push_str(&line);
+            } else {
+                doc = Some(line);
+            }
+        }
+        doc
+    }
+}
 impl Parsable for Expression {
     type Error = ParseError;
     fn parse(parser: &mut Parser) -> Result<Located<Self>, Located<Self::Error>> {
@@ -324,20 +452,21 @@ impl Expression {
             return Self::unary(parser, 0);
         };
         let mut left = Self::binary(parser, layer + 1)?;
-        while let Some(Located {
-            value: token,
-            pos: _,
-        }) = parser.peek()
-        {
+        while let Some(Located { value: token, pos }) = parser.peek() {
             let Some(op) = BinaryOperator::token(token) else {
                 break;
             };
             if !ops.contains(&op) {
                 break;
             }
+            // other operators can't start an expression, so they continue `left` on the next
+            // line, but a `-` there starts a new statement with a negation
+            if op == BinaryOperator::Minus && pos.start.ln != left.pos.end.ln {
+                break;
+            }
             parser.next();
-            let pos = left.pos.clone();
             let right = Self::binary(parser, layer + 1)?;
+            let pos = left.pos.to(&right.pos);
             left = Located::new(
                 Self::Binary {
                     op,
@@ -362,6 +491,7 @@ impl Expression {
                 if ops.contains(&op) {
                     let Located { value: _, pos } = expected!(parser);
                     let right = Self::unary(parser, layer)?;
+                    let pos = pos.to(&right.pos);
                     return Ok(Located::new(
                         Self::Unary {
                             op,
@@ -376,15 +506,12 @@ impl Expression {
     }
     pub fn call(parser: &mut Parser) -> Result<Located<Self>, Located<ParseError>> {
         let mut head = Atom::parse(parser)?.map(Self::Atom);
-        while let Some(Located {
-            value: token,
-            pos: _,
-        }) = parser.peek()
-        {
+        while let Some(Located { value: token, pos }) = parser.peek() {
             match token {
-                Token::ParanLeft => {
+                // a `(` or `[` on the next line starts a new statement instead of calling or
+                // indexing `head`, a `.` there continues it
+                Token::ParanLeft if pos.start.ln == head.pos.end.ln => {
                     parser.next();
-                    let pos = head.pos.clone();
                     let mut args = vec![];
                     while let Some(Located {
                         value: token,
@@ -403,7 +530,8 @@ impl Expression {
                         }
                         expected!(parser: Comma);
                     }
-                    expected!(parser: ParanRight);
+                    let Located { value: _, pos: end } = expected!(parser: ParanRight);
+                    let pos = head.pos.to(&end);
                     head = Located::new(
                         Self::Call {
                             head: Box::new(head),
@@ -412,6 +540,31 @@ impl Expression {
                         pos,
                     )
                 }
+                Token::BracketLeft if pos.start.ln == head.pos.end.ln => {
+                    parser.next();
+                    let index = Expression::parse(parser)?;
+                    let Located { value: _, pos: end } = expected!(parser: BracketRight);
+                    let pos = head.pos.to(&end);
+                    head = Located::new(
+                        Self::Index {
+                            head: Box::new(head),
+                            index: Box::new(index),
+                        },
+                        pos,
+                    )
+                }
+                Token::Dot => {
+                    parser.next();
+                    let field = Atom::ident(parser)?;
+                    let pos = head.pos.to(&field.pos);
+                    head = Located::new(
+                        Self::Field {
+                            head: Box::new(head),
+                            field,
+                        },
+                        pos,
+                    )
+                }
                 _ => break,
             }
         }
@@ -426,10 +579,116 @@ impl Parsable for Atom {
             Token::Ident(ident) => Ok(Located::new(Self::Ident(ident), pos)),
             Token::Number(number) => Ok(Located::new(Self::Number(number), pos)),
             Token::String(string) => Ok(Located::new(Self::String(string), pos)),
+            Token::Interpolation(parts) => {
+                let mut expr: Option<Located<Expression>> = None;
+                for part in parts {
+                    let part = match part {
+                        Interpolation::String(string) => {
+                            Located::new(Expression::Atom(Self::String(string)), pos.clone())
+                        }
+                        Interpolation::Tokens(tokens) => {
+                            let empty = tokens.is_empty();
+                            let mut nested = Parser::new(tokens);
+                            if empty {
+                                nested.eof = pos.clone();
+                            }
+                            let arg = Expression::parse(&mut nested);
+                            let trailing = nested.next();
+                            parser.errors.append(&mut nested.errors);
+                            let arg = arg?;
+                            if let Some(Located { value: token, pos }) = trailing {
+                                return Err(Located::new(ParseError::UnexpectedToken(token), pos));
+                            }
+                            let pos = arg.pos.clone();
+                            Located::new(
+                                Expression::Unary {
+                                    op: UnaryOperator::ToString,
+                                    right: Box::new(arg),
+                                },
+                                pos,
+                            )
+                        }
+                    };
+                    expr = Some(match expr {
+                        Some(left) => Located::new(
+                            Expression::Binary {
+                                op: BinaryOperator::Plus,
+                                left: Box::new(left),
+                                right: Box::new(part),
+                            },
+                            pos.clone(),
+                        ),
+                        None => part,
+                    });
+                }
+                let expr = expr.expect("empty interpolation");
+                Ok(Located::new(Self::Expression(Box::new(expr)), pos))
+            }
+            Token::True => Ok(Located::new(Self::Boolean(true), pos)),
+            Token::False => Ok(Located::new(Self::Boolean(false), pos)),
+            Token::Null => Ok(Located::new(Self::Null, pos)),
             Token::ParanLeft => {
                 let expr = Expression::parse(parser)?;
-                expected!(parser: ParanRight);
-                Ok(Located::new(Self::Expression(Box::new(expr)), pos))
+                let Located { value: _, pos: end } = expected!(parser: ParanRight);
+                Ok(Located::new(Self::Expression(Box::new(expr)), pos.to(&end)))
+            }
+            Token::BracketLeft => {
+                let mut items = vec![];
+                while let Some(Located {
+                    value: token,
+                    pos: _,
+                }) = parser.peek()
+                {
+                    if token == &Token::BracketRight {
+                        break;
+                    }
+                    items.push(Expression::parse(parser)?);
+                    if let Some(Located {
+                        value: Token::BracketRight,
+                        pos: _,
+                    }) = parser.peek() {
+                        break;
+                    }
+                    expected!(parser: Comma);
+                }
+                let Located { value: _, pos: end } = expected!(parser: BracketRight);
+                Ok(Located::new(Self::List(items), pos.to(&end)))
+            }
+            Token::BraceLeft => {
+                let mut entries = vec![];
+                while let Some(Located {
+                    value: token,
+                    pos: _,
+                }) = parser.peek()
+                {
+                    if token == &Token::BraceRight {
+                        break;
+                    }
+                    let Located { value: token, pos } = expected!(parser);
+                    let key = match token {
+                        Token::Ident(key) | Token::String(key) => Located::new(key, pos),
+                        token => {
+                            return Err(Located::new(
+                                ParseError::ExpectedToken {
+                                    expected: Token::Ident(Default::default()),
+                                    got: token,
+                                },
+                                pos,
+                            ))
+                        }
+                    };
+                    expected!(parser: Equal);
+                    entries.push((key, Expression::parse(parser)?));
+                    if let Some(Located {
+                        value: Token::BraceRight,
+                        pos: _,
+                    }) = parser.peek() {
+                        break;
+                    }
+                    expected!(parser: Comma);
+                }
+                let Located { value: _, pos: end } = expected!(parser: BraceRight);
+                Ok(Located::new(Self::Map(entries), pos.to(&end)))
             }
             token => Err(Located::new(ParseError::UnexpectedToken(token), pos)),
         }
@@ -459,6 +718,28 @@ impl Display for ParseError {
             ParseError::ExpectedToken { expected, got } => {
                 write!(f, "expected {}, got {}", expected.name(), got.name())
             }
+            ParseError::RequiredAfterDefault(ident) => {
+                write!(f, "parameter {ident:?} without a default follows one with a default")
+            }
+        }
+    }
+}
+impl Diagnose for ParseError {
+    fn label(&self) -> Option<String> {
+        match self {
+            ParseError::UnexpectedEOF => Some("file ends here".into()),
+            ParseError::ExpectedToken { expected, got: _ } => {
+                Some(format!("expected {}", expected.name()))
+            }
+            _ => None,
+        }
+    }
+    fn help(&self) -> Option<String> {
+        match self {
+            ParseError::RequiredAfterDefault(_) => {
+                Some("move it before the parameters with default values".into())
+            }
+            _ => None,
         }
     }
 }
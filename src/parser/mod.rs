@@ -1,10 +1,15 @@
 use crate::lexer::{position::Located, tokens::Token};
 use ast::{Chunk, ParseError};
-use parser::Parsable;
+use parser::{Parsable, Parser};
 
 pub mod ast;
 pub mod parser;
 
-pub fn parse(tokens: Vec<Located<Token>>) -> Result<Located<Chunk>, Located<ParseError>> {
-    Chunk::parse(&mut tokens.into_iter().peekable())
+pub fn parse(tokens: Vec<Located<Token>>) -> (Located<Chunk>, Vec<Located<ParseError>>) {
+    let mut parser = Parser::new(tokens);
+    let chunk = Chunk::parse(&mut parser).unwrap_or_else(|err| {
+        parser.errors.push(err);
+        Located::default()
+    });
+    (chunk, parser.errors)
 }
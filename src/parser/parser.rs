@@ -1,8 +1,81 @@
 use std::{iter::Peekable, vec::IntoIter};
-use crate::lexer::{position::Located, tokens::Token};
+use super::ast::ParseError;
+use crate::lexer::{
+    position::{Located, Span},
+    tokens::Token,
+};
 
-pub type Parser = Peekable<IntoIter<Located<Token>>>;
+pub struct Parser {
+    tokens: Peekable<IntoIter<Located<Token>>>,
+    consumed: usize,
+    pub eof: Span,
+    pub errors: Vec<Located<ParseError>>,
+}
+impl Parser {
+    pub fn new(tokens: Vec<Located<Token>>) -> Self {
+        let eof = tokens
+            .last()
+            .map(|token| Span::from(token.pos.end.clone()))
+            .unwrap_or_default();
+        Self {
+            tokens: tokens.into_iter().peekable(),
+            consumed: 0,
+            eof,
+            errors: vec![],
+        }
+    }
+    pub fn peek(&mut self) -> Option<&Located<Token>> {
+        self.tokens.peek()
+    }
+    pub fn consumed(&self) -> usize {
+        self.consumed
+    }
+    /// records `err` and skips ahead to the next statement keyword or `}`
+    pub fn recover(&mut self, err: Located<ParseError>, start: usize) {
+        if !matches!(
+            err.value,
+            ParseError::UnexpectedToken(Token::Error)
+                | ParseError::ExpectedToken {
+                    expected: _,
+                    got: Token::Error
+                }
+        ) {
+            self.errors.push(err);
+        }
+        if self.consumed == start {
+            self.next();
+        }
+        while let Some(Located { value: token, pos: _ }) = self.peek() {
+            if matches!(
+                token,
+                Token::Let
+                    | Token::Def
+                    | Token::Return
+                    | Token::If
+                    | Token::While
+                    | Token::For
+                    | Token::Break
+                    | Token::Continue
+                    | Token::Try
+                    | Token::Throw
+                    | Token::Doc(_)
+                    | Token::BraceRight
+            ) {
+                break;
+            }
+            self.next();
+        }
+    }
+}
+impl Iterator for Parser {
+    type Item = Located<Token>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.tokens.next()?;
+        self.consumed += 1;
+        Some(token)
+    }
+}
 pub trait Parsable: Sized {
     type Error;
     fn parse(parser: &mut Parser) -> Result<Located<Self>, Located<Self::Error>>;
-}
\ No newline at end of file
+}
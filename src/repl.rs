@@ -0,0 +1,118 @@
+use crate::{report, report_traced};
+use compiler::{
+    compiler::{
+        bytecode::Closure,
+        compiler::{Compilable, Compiler},
+    },
+    interpreter::{interpreter::Interpreter, std::std_globals, value::Value},
+    lexer::{complete, lex, position::Located},
+    parser::{ast::Chunk, parse},
+};
+use std::{
+    io::{self, Write},
+    mem,
+    rc::Rc,
+};
+
+const PATH: &str = "<repl>";
+const HELP: &str = "\
+:ast <code>       print the syntax tree of <code>
+:bytecode <code>  print the compiled bytecode of <code>
+:globals          list all global variables
+:help             show this message
+:quit             leave the REPL";
+
+pub fn repl() {
+    let mut interpreter = Interpreter::default();
+    std_globals(&mut interpreter.globals);
+    let mut input = String::new();
+    loop {
+        print!("{}", if input.is_empty() { "> " } else { ". " });
+        let _ = io::stdout().flush();
+        let mut line = String::new();
+        match io::stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if input.is_empty() {
+            if let Some(command) = line.trim().strip_prefix(':') {
+                if !meta(&mut interpreter, command) {
+                    break;
+                }
+                continue;
+            }
+        }
+        let blank = line.trim().is_empty();
+        input.push_str(&line);
+        if !blank && !complete(&input) {
+            continue;
+        }
+        let text = mem::take(&mut input);
+        if !text.trim().is_empty() {
+            eval(&mut interpreter, &text);
+        }
+    }
+    println!();
+}
+
+/// returns false if the REPL should exit
+fn meta(interpreter: &mut Interpreter, command: &str) -> bool {
+    let (command, code) = command.split_once(' ').unwrap_or((command, ""));
+    match command {
+        "ast" => {
+            if let Some(chunk) = ast(code) {
+                println!("{chunk:#?}");
+            }
+        }
+        "bytecode" => {
+            if let Some(closure) = ast(code).and_then(|ast| compile(code, ast)) {
+                print!("{closure}");
+            }
+        }
+        "globals" => {
+            let mut globals: Vec<_> = interpreter.globals.iter().collect();
+            globals.sort_by_key(|(ident, _)| *ident);
+            for (ident, value) in globals {
+                println!("{ident} = {value:?}");
+            }
+        }
+        "help" => println!("{HELP}"),
+        "quit" | "q" => return false,
+        _ => eprintln!("unknown command :{command}, try :help"),
+    }
+    true
+}
+
+fn ast(text: &str) -> Option<Located<Chunk>> {
+    let (tokens, lex_errors) = lex(text);
+    let (chunk, parse_errors) = parse(tokens);
+    if !lex_errors.is_empty() || !parse_errors.is_empty() {
+        report(PATH, text, &lex_errors);
+        report(PATH, text, &parse_errors);
+        return None;
+    }
+    Some(chunk)
+}
+
+fn compile(text: &str, chunk: Located<Chunk>) -> Option<Closure> {
+    let mut compiler = Compiler {
+        top_level_globals: true,
+        path: PATH.into(),
+        ..Default::default()
+    };
+    chunk
+        .compile(&mut compiler)
+        .map_err(|err| report(PATH, text, &[err]))
+        .ok()
+}
+
+fn eval(interpreter: &mut Interpreter, text: &str) {
+    let Some(closure) = ast(text).and_then(|ast| compile(text, ast)) else {
+        return;
+    };
+    match interpreter.run(&Rc::new(closure)) {
+        Ok(Some(Value::Null) | None) => {}
+        Ok(Some(value)) => println!("{value:?}"),
+        Err(err) => report_traced(PATH, text, err),
+    }
+}
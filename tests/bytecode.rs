@@ -0,0 +1,209 @@
+use compiler::{
+    compiler::{
+        bytecode::{ByteCode, Closure},
+        compile,
+        serialize::{deserialize, serialize, LoadError, MAGIC, MAX_NESTING, VERSION},
+    },
+    interpreter::{interpreter::RunTimeError, run},
+    lexer::{lex, position::Located},
+    parser::parse,
+};
+use std::rc::Rc;
+
+fn closure(text: &str) -> Closure {
+    let (tokens, errors) = lex(text);
+    assert!(errors.is_empty(), "lex errors: {errors:?}");
+    let (chunk, errors) = parse(tokens);
+    assert!(errors.is_empty(), "parse errors: {errors:?}");
+    compile(chunk).expect("compile error")
+}
+
+const PROGRAM: &str = r#"
+let names = ["a", "b"]
+def counter(start) {
+    let n = start
+    def next() {
+        n = n + 1.5
+        return n
+    }
+    return next
+}
+let next = counter(0)
+for name in names {
+    if name == "b" & !false { break }
+}
+{ key = next() }.key
+"#;
+
+const DEFAULTS: &str = r#"
+def f(a, b = a + 1, ...rest) {
+    return [a, b, rest]
+}
+f(1)
+"#;
+
+const TRY: &str = r#"
+for i in [1, 2] {
+    try {
+        if i == 2 { break }
+        throw "x"
+    } catch err { throw err }
+}
+"#;
+
+fn assert_round_trip(text: &str) {
+    let closure = closure(text);
+    let loaded = deserialize(&serialize(&closure)).expect("load error");
+    assert_eq!(loaded, closure);
+    let positions = |closure: &Closure| {
+        closure
+            .code
+            .iter()
+            .map(|bytecode| bytecode.pos.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(positions(&loaded), positions(&closure));
+}
+
+#[test]
+fn round_trip() {
+    assert_round_trip(PROGRAM);
+}
+
+#[test]
+fn round_trip_defaults_and_rest() {
+    assert_round_trip(DEFAULTS);
+}
+
+#[test]
+fn round_trip_try() {
+    assert_round_trip(TRY);
+}
+
+#[test]
+fn rejects_bad_header() {
+    let mut bytes = serialize(&closure("1"));
+    assert_eq!(deserialize(&bytes[1..]), Err(LoadError::BadMagic));
+    bytes[MAGIC.len()] = VERSION as u8 + 1;
+    assert_eq!(
+        deserialize(&bytes),
+        Err(LoadError::UnsupportedVersion(VERSION + 1))
+    );
+}
+
+#[test]
+fn rejects_truncated_and_trailing_bytes() {
+    let mut bytes = serialize(&closure(PROGRAM));
+    assert_eq!(
+        deserialize(&bytes[..bytes.len() - 1]),
+        Err(LoadError::UnexpectedEnd)
+    );
+    bytes.push(0);
+    assert_eq!(deserialize(&bytes), Err(LoadError::TrailingBytes));
+}
+
+#[test]
+fn rejects_out_of_range_operands() {
+    let mut closure = closure("1");
+    closure.code.insert(
+        0,
+        Located::new(ByteCode::Move { dst: 0, src: 100 }, Default::default()),
+    );
+    assert_eq!(
+        deserialize(&serialize(&closure)),
+        Err(LoadError::InvalidRegister {
+            addr: 0,
+            register: 100
+        })
+    );
+    closure.code[0].value = ByteCode::Jump { addr: 100 };
+    assert_eq!(
+        deserialize(&serialize(&closure)),
+        Err(LoadError::InvalidAddress {
+            addr: 0,
+            target: 100
+        })
+    );
+}
+
+#[test]
+fn rejects_code_without_return() {
+    let mut closure = closure("1");
+    closure.code.pop();
+    assert_eq!(
+        deserialize(&serialize(&closure)),
+        Err(LoadError::MissingReturn)
+    );
+    let err = run(&Rc::new(closure.clone())).expect_err("expected a runtime error");
+    assert_eq!(err.err.value, RunTimeError::EndOfCode);
+    closure.code.clear();
+    assert_eq!(
+        deserialize(&serialize(&closure)),
+        Err(LoadError::MissingReturn)
+    );
+    let err = run(&Rc::new(closure)).expect_err("expected a runtime error");
+    assert_eq!(err.err.value, RunTimeError::EndOfCode);
+}
+
+#[test]
+fn rejects_deeply_nested_closures() {
+    let nest = |depth: usize| {
+        let mut nested = closure("1");
+        for _ in 0..depth {
+            let mut outer = closure("1");
+            outer.closures.push(Rc::new(nested));
+            nested = outer;
+        }
+        nested
+    };
+    let nested = nest(MAX_NESTING);
+    assert_eq!(deserialize(&serialize(&nested)), Ok(nested));
+    assert_eq!(
+        deserialize(&serialize(&nest(MAX_NESTING + 1))),
+        Err(LoadError::TooDeeplyNested)
+    );
+}
+
+#[test]
+fn iterating_a_non_iterator_is_a_runtime_error() {
+    let mut closure = closure("1");
+    closure.code.insert(
+        0,
+        Located::new(
+            ByteCode::IterNext {
+                dst: 0,
+                iter: 0,
+                addr: 1,
+            },
+            Default::default(),
+        ),
+    );
+    let loaded = deserialize(&serialize(&closure)).expect("load error");
+    let err = run(&Rc::new(loaded)).expect_err("expected a runtime error");
+    assert_eq!(err.err.value, RunTimeError::CannotIterate("null"));
+}
+
+#[test]
+fn disassemble() {
+    let text = "let x = \"x\"\ndef f(a) {\n    return x + a\n}\ny = f(1)";
+    assert_eq!(
+        closure(text).to_string(),
+        r#"closure <main> (0 params, 6 registers, 0 upvalues, 8 instructions)
+  0000  1:9     String { dst: 2, addr: 0 }                   ; "x"
+  0001  1:1     Move { dst: 1, src: 2 }
+  0002  2:1     Closure { dst: 0, addr: 0 }                  ; closure f
+  0003  5:7     Number { dst: 5, addr: 0 }                   ; 1
+  0004  5:7     Move { dst: 4, src: 5 }
+  0005  5:5     Call { func: 0, offset: 4, args_len: 1, dst: Some(3) }
+  0006  5:1     SetGlobal { addr: 1, src: 3 }                ; "y"
+  0007  1:1     Return { src: None }
+
+closure f (1 params, 3 registers, 1 upvalues, 4 instructions)
+  0000  3:12    GetUpvalue { dst: 2, addr: 0 }               ; Local(1)
+  0001  3:12    Binary { op: Add, dst: 1, left: 2, right: 0 }
+  0002  3:5     Return { src: Some(1) }
+  0003  2:1     Return { src: None }
+  upvalue 0: Local(1)
+"#
+    );
+}
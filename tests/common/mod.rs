@@ -0,0 +1,31 @@
+#![allow(dead_code)]
+
+use compiler::{
+    compiler::compile,
+    interpreter::{
+        interpreter::{Interpreter, TracedError},
+        std::std_globals,
+        value::Value,
+    },
+    lexer::lex,
+    parser::parse,
+};
+use std::rc::Rc;
+
+pub fn eval(text: &str) -> Value {
+    try_eval(text).expect("runtime error")
+}
+
+pub fn try_eval(text: &str) -> Result<Value, TracedError> {
+    let (tokens, errors) = lex(text);
+    assert!(errors.is_empty(), "lex errors: {errors:?}");
+    let (chunk, errors) = parse(tokens);
+    assert!(errors.is_empty(), "parse errors: {errors:?}");
+    let closure = compile(chunk).expect("compile error");
+    let mut interpreter = Interpreter::default();
+    std_globals(&mut interpreter.globals);
+    let value = interpreter.run(&Rc::new(closure))?;
+    Ok(value
+        .or_else(|| interpreter.globals.remove("result"))
+        .unwrap_or_default())
+}
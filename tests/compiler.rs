@@ -0,0 +1,132 @@
+use compiler::{
+    compiler::{
+        bytecode::ByteCode,
+        compile,
+        compiler::{Compilable, CompileError, Compiler},
+    },
+    lexer::{lex, position::Span},
+    parser::parse,
+};
+
+fn compile_with(text: &str, mut compiler: Compiler) -> CompileError {
+    let (tokens, errors) = lex(text);
+    assert!(errors.is_empty(), "lex errors: {errors:?}");
+    let (chunk, errors) = parse(tokens);
+    assert!(errors.is_empty(), "parse errors: {errors:?}");
+    chunk
+        .compile(&mut compiler)
+        .expect_err("expected a compile error")
+        .value
+}
+
+fn compile_error(text: &str) -> CompileError {
+    compile_with(text, Compiler::default())
+}
+
+#[test]
+fn too_many_list_items() {
+    let text = format!("[{}]", vec!["0"; 70000].join(", "));
+    assert_eq!(compile_error(&text), CompileError::TooManyItems(70000));
+}
+
+#[test]
+fn undeclared_variable_in_strict_mode() {
+    let strict = Compiler {
+        strict: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        compile_with("let a = 1\nb = a", strict),
+        CompileError::UndeclaredVariable("b".into())
+    );
+}
+
+#[test]
+fn duplicate_parameter() {
+    assert_eq!(
+        compile_error("def f(a, b, a) {}"),
+        CompileError::DuplicateParameter("a".into())
+    );
+    assert_eq!(
+        compile_error("def f(a, ...a) {}"),
+        CompileError::DuplicateParameter("a".into())
+    );
+}
+
+#[test]
+fn return_outside_function() {
+    assert_eq!(
+        compile_error("return 1"),
+        CompileError::ReturnOutsideFunction
+    );
+}
+
+#[test]
+fn too_many_arguments() {
+    let text = format!("print({})", vec!["0"; 256].join(", "));
+    assert_eq!(compile_error(&text), CompileError::TooManyArguments(256));
+}
+
+#[test]
+fn too_many_parameters() {
+    let params: Vec<_> = (0..256).map(|i| format!("p{i}")).collect();
+    let text = format!("def f({}) {{}}", params.join(", "));
+    assert_eq!(compile_error(&text), CompileError::TooManyParameters(256));
+}
+
+#[test]
+fn too_many_registers() {
+    let text: String = (0..40000).map(|i| format!("let a{i} = 0\n")).collect();
+    assert_eq!(compile_error(&text), CompileError::TooManyRegisters);
+}
+
+#[test]
+fn assign_to_literal() {
+    assert_eq!(
+        compile_error("true = 1"),
+        CompileError::AssignToLiteral("true")
+    );
+    assert_eq!(
+        compile_error("false = 1"),
+        CompileError::AssignToLiteral("false")
+    );
+    assert_eq!(
+        compile_error("null = 1"),
+        CompileError::AssignToLiteral("null")
+    );
+}
+
+#[test]
+fn break_and_continue_outside_loop() {
+    assert_eq!(compile_error("break"), CompileError::BreakOutsideLoop);
+    assert_eq!(compile_error("continue"), CompileError::ContinueOutsideLoop);
+    assert_eq!(
+        compile_error("while true {\n  def f() { break }\n}"),
+        CompileError::BreakOutsideLoop
+    );
+}
+
+#[test]
+fn bytecode_carries_expression_spans() {
+    let (tokens, _) = lex("def f(x) {\n    return x\n}\nf(1) + f(22)");
+    let (chunk, _) = parse(tokens);
+    let closure = compile(chunk).expect("compile error");
+    let spans: Vec<_> = closure
+        .code
+        .iter()
+        .filter(|bytecode| {
+            matches!(
+                bytecode.value,
+                ByteCode::Call { .. } | ByteCode::Binary { .. }
+            )
+        })
+        .map(|bytecode| {
+            let Span { start, end } = &bytecode.pos;
+            ((start.ln, start.col), (end.ln, end.col))
+        })
+        .collect();
+    assert_eq!(
+        spans,
+        [((3, 0), (3, 4)), ((3, 7), (3, 12)), ((3, 0), (3, 12))]
+    );
+}
@@ -0,0 +1,160 @@
+use compiler::{
+    diagnostic::diagnostic::Diagnostic,
+    lexer::position::{Position, Span},
+};
+
+fn diagnostic(start: (usize, usize), end: (usize, usize)) -> Diagnostic {
+    Diagnostic {
+        message: "something went wrong".into(),
+        span: Span::new(
+            Position::new(start.0, start.1, 0),
+            Position::new(end.0, end.1, 0),
+        ),
+        label: None,
+        notes: vec![],
+        help: None,
+    }
+}
+
+#[test]
+fn single_line_span() {
+    let text = "let a = 1\nlet b = a + {}";
+    assert_eq!(
+        diagnostic((1, 8), (1, 14)).render("main", text, false),
+        "\
+error: something went wrong
+ --> main:2:9
+  |
+2 | let b = a + {}
+  |         ^^^^^^
+"
+    );
+}
+
+#[test]
+fn empty_span_has_one_caret() {
+    assert_eq!(
+        diagnostic((0, 3), (0, 3)).render("main", "abc", false),
+        "\
+error: something went wrong
+ --> main:1:4
+  |
+1 | abc
+  |    ^
+"
+    );
+}
+
+#[test]
+fn multi_line_span_underlines_to_end_of_first_line() {
+    let text = "let a = [\n  1\n]";
+    assert_eq!(
+        diagnostic((0, 8), (2, 1)).render("main", text, false),
+        "\
+error: something went wrong
+ --> main:1:9
+  |
+1 | let a = [
+  |         ^
+"
+    );
+    let text = "let a = f(1,\n  2)";
+    assert_eq!(
+        diagnostic((0, 8), (1, 4)).render("main", text, false),
+        "\
+error: something went wrong
+ --> main:1:9
+  |
+1 | let a = f(1,
+  |         ^^^^
+"
+    );
+}
+
+#[test]
+fn gutter_grows_with_line_number() {
+    let text = "\n".repeat(9) + "oops";
+    assert_eq!(
+        diagnostic((9, 0), (9, 4)).render("main", &text, false),
+        "\
+error: something went wrong
+  --> main:10:1
+   |
+10 | oops
+   | ^^^^
+"
+    );
+}
+
+#[test]
+fn label_notes_and_help() {
+    let mut diagnostic = diagnostic((0, 4), (0, 5));
+    diagnostic.label = Some("this one".into());
+    diagnostic.notes = vec!["first note".into(), "second note".into()];
+    diagnostic.help = Some("try this".into());
+    assert_eq!(
+        diagnostic.render("main", "let a", false),
+        "\
+error: something went wrong
+ --> main:1:5
+  |
+1 | let a
+  |     ^ this one
+  |
+  = note: first note
+  = note: second note
+  = help: try this
+"
+    );
+}
+
+#[test]
+fn carets_keep_tabs() {
+    let text = "def f(a) {\n\tthrow \"x\"\n}";
+    assert_eq!(
+        diagnostic((1, 1), (1, 10)).render("main", text, false),
+        "\
+error: something went wrong
+ --> main:2:2
+  |
+2 | \tthrow \"x\"
+  | \t^^^^^^^^^
+"
+    );
+    let text = "\t \tx";
+    assert!(diagnostic((0, 3), (0, 4))
+        .render("main", text, false)
+        .ends_with("  | \t \t^\n"));
+}
+
+#[test]
+fn color() {
+    let mut diagnostic = diagnostic((0, 0), (0, 1));
+    diagnostic.label = Some("here".into());
+    diagnostic.help = Some("try this".into());
+    let plain = diagnostic.render("main", "x", false);
+    assert!(!plain.contains('\x1b'));
+    let colored = diagnostic.render("main", "x", true);
+    assert!(colored.starts_with("\x1b[1;31merror\x1b[0m"));
+    assert!(colored.contains("\x1b[1;31m^ here\x1b[0m"));
+    let stripped = colored
+        .split('\x1b')
+        .enumerate()
+        .map(|(i, part)| {
+            if i == 0 {
+                part
+            } else {
+                &part[part.find('m').unwrap() + 1..]
+            }
+        })
+        .collect::<String>();
+    assert_eq!(stripped, plain);
+}
+
+#[test]
+fn display_is_one_line() {
+    assert_eq!(
+        diagnostic((1, 8), (1, 14)).to_string(),
+        "2:9: something went wrong"
+    );
+}
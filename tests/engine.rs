@@ -0,0 +1,152 @@
+use compiler::{
+    engine::{
+        convert::{FromValue, IntoValue},
+        engine::{Engine, EngineError},
+    },
+    interpreter::{
+        interpreter::{Interpreter, NativeError, RunTimeError},
+        value::Value,
+    },
+};
+use std::{cell::Cell, collections::HashMap, io, rc::Rc};
+
+#[test]
+fn globals_persist_between_evals() {
+    let mut engine = Engine::new();
+    engine.eval("let count = 1").unwrap();
+    engine.eval("count = count + 1").unwrap();
+    assert_eq!(engine.get_global::<i64>("count").unwrap(), 2);
+    assert_eq!(engine.eval("count * 10").unwrap(), Value::Number(20.));
+}
+
+#[test]
+fn set_and_get_globals() {
+    let mut engine = Engine::new();
+    engine.set_global("names", vec!["a", "b"]);
+    engine.set_global("limits", HashMap::from([("max".to_string(), 3)]));
+    engine
+        .eval("push(names, \"c\")\nlimits.max = limits.max + 1")
+        .unwrap();
+    assert_eq!(
+        engine.get_global::<Vec<String>>("names").unwrap(),
+        ["a", "b", "c"]
+    );
+    let limits: HashMap<String, u32> = engine.get_global("limits").unwrap();
+    assert_eq!(limits["max"], 4);
+    assert!(matches!(
+        engine.get_global::<Value>("missing"),
+        Err(EngineError::UndefinedGlobal(_))
+    ));
+    assert!(matches!(
+        engine.get_global::<bool>("names"),
+        Err(EngineError::Conversion(_))
+    ));
+}
+
+#[test]
+fn call_script_functions() {
+    let mut engine = Engine::new();
+    engine
+        .eval("def greet(name, greeting = \"hello\") { return \"{greeting} {name}\" }")
+        .unwrap();
+    let text: String = engine
+        .call_function("greet", vec!["world".into_value()])
+        .unwrap();
+    assert_eq!(text, "hello world");
+    let err = engine.call_function::<Value>("greet", vec![]).unwrap_err();
+    assert!(matches!(
+        err,
+        EngineError::Runtime(ref err) if matches!(err.err.value, RunTimeError::Arity { .. })
+    ));
+}
+
+#[test]
+fn register_native_functions() {
+    fn double(_: &mut Interpreter, args: Vec<Value>) -> Result<Value, NativeError> {
+        let number = f64::from_value(args.into_iter().next().unwrap_or_default())?;
+        Ok((number * 2.).into_value())
+    }
+    let mut engine = Engine::new();
+    engine.register_fn("double", double);
+    assert_eq!(engine.eval("double(21)").unwrap(), Value::Number(42.));
+    assert_eq!(
+        engine
+            .call_function::<i32>("double", vec![4.into_value()])
+            .unwrap(),
+        8
+    );
+    assert!(engine.eval("double(\"a\")").is_err());
+}
+
+#[test]
+fn register_capturing_closures() {
+    let calls = Rc::new(Cell::new(0));
+    let mut engine = Engine::new();
+    engine.register_fn("tick", {
+        let calls = calls.clone();
+        move |_, _| {
+            calls.set(calls.get() + 1);
+            Ok(calls.get().into_value())
+        }
+    });
+    assert_eq!(engine.eval("tick()\ntick()").unwrap(), Value::Number(2.));
+    assert_eq!(calls.get(), 2);
+}
+
+#[test]
+fn integer_conversion_bounds() {
+    let max = 2f64.powi(63);
+    assert!(i64::from_value(Value::Number(max)).is_err());
+    assert!(i64::from_value(Value::Number(-max)).is_ok());
+    assert!(u64::from_value(Value::Number(max)).is_ok());
+    assert!(u64::from_value(Value::Number(max * 2.)).is_err());
+    assert!(u64::from_value(Value::Number(-1.)).is_err());
+    assert_eq!(
+        i32::from_value(Value::Number(i32::MAX as f64)),
+        Ok(i32::MAX)
+    );
+    assert_eq!(
+        i32::from_value(Value::Number(i32::MIN as f64)),
+        Ok(i32::MIN)
+    );
+    assert!(i32::from_value(Value::Number(i32::MAX as f64 + 1.)).is_err());
+    assert!(i32::from_value(Value::Number(i32::MIN as f64 - 1.)).is_err());
+    assert_eq!(
+        u32::from_value(Value::Number(u32::MAX as f64)),
+        Ok(u32::MAX)
+    );
+    assert!(u32::from_value(Value::Number(u32::MAX as f64 + 1.)).is_err());
+    assert!(i64::from_value(Value::Number(1.5)).is_err());
+}
+
+#[test]
+fn reports_syntax_errors() {
+    let mut engine = Engine::new();
+    let err = engine.eval("let = 1").unwrap_err();
+    assert!(matches!(err, EngineError::Syntax { .. }));
+    assert_eq!(err.to_string(), "1:5: expected identifier, got \"=\"");
+}
+
+#[test]
+fn io_errors_keep_their_kind() {
+    let mut engine = Engine::new();
+    engine.register_fn("open", |_, _| {
+        Err(io::Error::new(io::ErrorKind::NotFound, "no such file").into())
+    });
+    let err = engine.eval("open()").unwrap_err();
+    assert!(matches!(
+        err,
+        EngineError::Runtime(ref err) if err.err.value == RunTimeError::IO {
+            kind: io::ErrorKind::NotFound,
+            message: "no such file".into(),
+        }
+    ));
+    assert_eq!(err.to_string(), "1:1: io error: no such file");
+    let value = engine
+        .eval("let r = null\ntry { open() } catch err { r = [err.kind, err.message] }\nr")
+        .unwrap();
+    assert_eq!(
+        format!("{value:?}"),
+        r#"["NotFound", "io error: no such file"]"#
+    );
+}
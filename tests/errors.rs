@@ -0,0 +1,137 @@
+mod common;
+use common::{eval, try_eval};
+use compiler::interpreter::{
+    interpreter::{Arity, RunTimeError},
+    value::Value,
+};
+
+#[test]
+fn trace_lists_frames_innermost_first() {
+    let err = try_eval(
+        "def inner(x) {\n    return x + {}\n}\ndef outer() {\n    return inner(1)\n}\nouter()",
+    )
+    .expect_err("expected a runtime error");
+    let frames: Vec<_> = err
+        .trace
+        .iter()
+        .map(|frame| (frame.name.as_str(), frame.pos.start.ln))
+        .collect();
+    assert_eq!(frames, [("inner", 1), ("outer", 4), ("<main>", 6)]);
+}
+
+#[test]
+fn native_wrong_type() {
+    let err = try_eval("len(1)").expect_err("expected a runtime error");
+    assert_eq!(
+        err.err.value,
+        RunTimeError::WrongType {
+            arg: 0,
+            expected: "string, list or map",
+            got: "number",
+        }
+    );
+}
+
+#[test]
+fn native_arity() {
+    let err = try_eval("range()").expect_err("expected a runtime error");
+    assert_eq!(
+        err.err.value,
+        RunTimeError::Arity {
+            expected: Arity::range(1, 3),
+            got: 0,
+            name: None,
+        }
+    );
+    assert_eq!(
+        err.err.value.to_string(),
+        "expected 1 to 3 arguments, got 0"
+    );
+}
+
+#[test]
+fn native_value_error() {
+    let err = try_eval("range(0, 1, 0)").expect_err("expected a runtime error");
+    assert_eq!(
+        err.err.value,
+        RunTimeError::Value("range step cannot be zero".into())
+    );
+}
+
+#[test]
+fn closure_arity() {
+    let err = try_eval("def f(a, b = 1) {}\nf(1, 2, 3)").expect_err("expected a runtime error");
+    assert_eq!(
+        err.err.value,
+        RunTimeError::Arity {
+            expected: Arity::range(1, 2),
+            got: 3,
+            name: Some("f".into()),
+        }
+    );
+    assert!(try_eval("def f(a, ...rest) {}\nf()").is_err());
+}
+
+#[test]
+fn catches_builtin_errors() {
+    let value = eval(
+        "def f() { return 1 + {} }
+        try { f() } catch err { result = [err.message, err.line, err.column] }",
+    );
+    assert_eq!(
+        format!("{value:?}"),
+        r#"["cannot perform binary operation Add on number with map", 1.0, 18.0]"#
+    );
+}
+
+#[test]
+fn catches_thrown_values() {
+    let value = eval(
+        "def f(n) { if n > 1 { throw n * 10 } return f(n + 1) }
+        try { f(0) } catch err { result = err }",
+    );
+    assert_eq!(value, Value::Number(20.));
+}
+
+#[test]
+fn uncaught_throw() {
+    let err = try_eval("try { throw 1 } catch err { throw err + 1 }")
+        .expect_err("expected a runtime error");
+    assert_eq!(err.err.value, RunTimeError::Thrown(Value::Number(2.)));
+}
+
+#[test]
+fn leaving_try_removes_handler() {
+    let err = try_eval(
+        "for i in range(2) { try { continue } catch err {} }
+        while true { try { break } catch err {} }
+        1 + {}",
+    )
+    .expect_err("expected a runtime error");
+    assert_eq!(err.err.pos.start.ln, 2);
+}
+
+#[test]
+fn catch_keeps_captured_registers() {
+    let value = eval(
+        "let fs = []
+        try {
+            def g() { return g }
+            push(fs, g)
+            throw \"boom\"
+        } catch err { result = [err, fs[0]() == fs[0]] }",
+    );
+    assert_eq!(format!("{value:?}"), r#"["boom", true]"#);
+    let value = eval(
+        "let fs = []
+        try {
+            if true {
+                let x = 1
+                def g() { return x }
+                push(fs, g)
+                throw \"boom\"
+            }
+        } catch err { result = [err, fs[0]()] }",
+    );
+    assert_eq!(format!("{value:?}"), r#"["boom", 1.0]"#);
+}
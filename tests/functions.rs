@@ -0,0 +1,168 @@
+mod common;
+
+use common::eval;
+use compiler::interpreter::value::Value;
+
+#[test]
+fn factorial() {
+    let value = eval(
+        "def fac(n) {
+            if n <= 1 { return 1 }
+            return n * fac(n - 1)
+        }
+        result = fac(10)",
+    );
+    assert_eq!(value, Value::Number(3628800.));
+}
+
+#[test]
+fn fibonacci() {
+    let value = eval(
+        "def fib(n) {
+            if n < 2 { return n }
+            return fib(n - 1) + fib(n - 2)
+        }
+        result = fib(15)",
+    );
+    assert_eq!(value, Value::Number(610.));
+}
+
+#[test]
+fn nested_recursion() {
+    let value = eval(
+        "def outer(n) {
+            def sum(n) {
+                if n == 0 { return 0 }
+                return n + sum(n - 1)
+            }
+            return sum(n)
+        }
+        result = outer(100)",
+    );
+    assert_eq!(value, Value::Number(5050.));
+}
+
+#[test]
+fn mutual_recursion() {
+    let value = eval(
+        "def even(n) {
+            if n == 0 { return 1 }
+            return odd(n - 1)
+        }
+        def odd(n) {
+            if n == 0 { return 0 }
+            return even(n - 1)
+        }
+        result = even(10) + odd(7) * 10 + even(3) * 100",
+    );
+    assert_eq!(value, Value::Number(11.));
+}
+
+#[test]
+fn mutual_recursion_in_block() {
+    let value = eval(
+        "def parity(n) {
+            def even(n) {
+                if n == 0 { return 1 }
+                return odd(n - 1)
+            }
+            def odd(n) {
+                if n == 0 { return 0 }
+                return even(n - 1)
+            }
+            return even(n)
+        }
+        result = parity(4) + parity(5) * 10",
+    );
+    assert_eq!(value, Value::Number(1.));
+}
+
+#[test]
+fn default_parameters() {
+    let value = eval(
+        "def f(a, b = a * 2, c = b + 1) {
+            return [a, b, c]
+        }
+        result = [f(1), f(1, 5), f(1, 5, 0)]",
+    );
+    assert_eq!(format!("{value:?}"), "[[1.0, 2.0, 3.0], [1.0, 5.0, 6.0], [1.0, 5.0, 0.0]]");
+}
+
+#[test]
+fn variadic_parameters() {
+    let value = eval(
+        "def f(a, ...rest) {
+            return [a, rest]
+        }
+        result = [f(1), f(1, 2, 3)]",
+    );
+    assert_eq!(format!("{value:?}"), "[[1.0, []], [1.0, [2.0, 3.0]]]");
+}
+
+#[test]
+fn upvalues_share_mutation() {
+    let value = eval(
+        "def counter() {
+            let n = 0
+            def inc() { n = n + 1 return n }
+            def get() { return n }
+            return [inc, get]
+        }
+        let fs = counter()
+        fs[0]()
+        fs[0]()
+        result = fs[1]()",
+    );
+    assert_eq!(value, Value::Number(2.));
+}
+
+#[test]
+fn loop_iterations_capture_fresh_variables() {
+    let value = eval(
+        "let fs = []
+        for i in range(3) {
+            let x = i * 10
+            def get() { return x }
+            push(fs, get)
+        }
+        result = [fs[0](), fs[1](), fs[2]()]",
+    );
+    assert_eq!(format!("{value:?}"), "[0.0, 10.0, 20.0]");
+}
+
+#[test]
+fn block_exit_closes_captured_variables() {
+    let value = eval(
+        "let fs = []
+        if true {
+            let a = 1
+            def get() { return a }
+            push(fs, get)
+        }
+        if true {
+            let b = 2
+            def get() { return b }
+            push(fs, get)
+        }
+        result = [fs[0](), fs[1]()]",
+    );
+    assert_eq!(format!("{value:?}"), "[1.0, 2.0]");
+}
+
+#[test]
+fn break_and_continue_close_captured_variables() {
+    let value = eval(
+        "let fs = []
+        for i in range(4) {
+            let x = i
+            def get() { return x }
+            push(fs, get)
+            if i == 1 { continue }
+            if i == 2 { break }
+        }
+        let y = 100
+        let z = 200
+        result = [fs[0](), fs[1](), fs[2]()]",
+    );
+    assert_eq!(format!("{value:?}"), "[0.0, 1.0, 2.0]");
+}
@@ -0,0 +1,321 @@
+use compiler::lexer::{
+    complete, lex,
+    tokens::{LexError, Token},
+};
+
+fn tokens(text: &str) -> Vec<Token> {
+    let (tokens, errors) = lex(text);
+    assert!(errors.is_empty(), "lex errors: {errors:?}");
+    tokens.into_iter().map(|token| token.value).collect()
+}
+
+fn number(text: &str) -> f64 {
+    match tokens(text)[..] {
+        [Token::Number(number)] => number,
+        ref tokens => panic!("expected a single number, got {tokens:?}"),
+    }
+}
+
+fn error(text: &str) -> LexError {
+    let (_, errors) = lex(text);
+    errors.into_iter().next().expect("expected lex error").value
+}
+
+#[test]
+fn decimal() {
+    assert_eq!(number("0"), 0.);
+    assert_eq!(number("42"), 42.);
+    assert_eq!(number("3.25"), 3.25);
+    assert_eq!(number("007"), 7.);
+}
+
+#[test]
+fn trailing_dot() {
+    assert_eq!(number("1."), 1.);
+    assert_eq!(tokens("1..5"), vec![Token::Number(1.), Token::Number(0.5)]);
+}
+
+#[test]
+fn leading_dot() {
+    assert_eq!(number(".5"), 0.5);
+    assert_eq!(number(".25e2"), 25.);
+    assert_eq!(
+        tokens("a.b"),
+        vec![
+            Token::Ident("a".into()),
+            Token::Dot,
+            Token::Ident("b".into())
+        ]
+    );
+}
+
+#[test]
+fn underscores() {
+    assert_eq!(number("1_000_000"), 1_000_000.);
+    assert_eq!(number("1_000.000_1"), 1_000.000_1);
+    assert_eq!(number("0xFF_FF"), 65535.);
+    assert_eq!(number("0b1010_1010"), 170.);
+}
+
+#[test]
+fn exponent() {
+    assert_eq!(number("1e-9"), 1e-9);
+    assert_eq!(number("1E+3"), 1000.);
+    assert_eq!(number("2.5e2"), 250.);
+    assert_eq!(number("1.e5"), 1e5);
+    assert_eq!(error("1e"), LexError::EmptyExponent);
+    assert_eq!(error("1e+"), LexError::EmptyExponent);
+    assert_eq!(error("1ex"), LexError::EmptyExponent);
+}
+
+#[test]
+fn hexadecimal() {
+    assert_eq!(number("0xFF"), 255.);
+    assert_eq!(number("0Xff"), 255.);
+    assert_eq!(number("0x0"), 0.);
+    assert_eq!(
+        error("0xFG"),
+        LexError::InvalidDigit {
+            digit: 'G',
+            radix: 16
+        }
+    );
+    assert_eq!(error("0x"), LexError::MissingDigits { radix: 16 });
+}
+
+#[test]
+fn binary_and_octal() {
+    assert_eq!(number("0b1010"), 10.);
+    assert_eq!(number("0o17"), 15.);
+    assert_eq!(
+        error("0b102"),
+        LexError::InvalidDigit {
+            digit: '2',
+            radix: 2
+        }
+    );
+    assert_eq!(
+        error("0o8"),
+        LexError::InvalidDigit {
+            digit: '8',
+            radix: 8
+        }
+    );
+    assert_eq!(error("0b_"), LexError::MissingDigits { radix: 2 });
+}
+
+#[test]
+fn trailing_letters() {
+    assert_eq!(
+        error("12abc"),
+        LexError::InvalidDigit {
+            digit: 'a',
+            radix: 10
+        }
+    );
+}
+
+#[test]
+fn expressions() {
+    assert_eq!(
+        tokens("0x10+1_0"),
+        vec![Token::Number(16.), Token::Plus, Token::Number(10.)]
+    );
+    assert_eq!(
+        tokens("f(1.,.5)"),
+        vec![
+            Token::Ident("f".into()),
+            Token::ParanLeft,
+            Token::Number(1.),
+            Token::Comma,
+            Token::Number(0.5),
+            Token::ParanRight
+        ]
+    );
+}
+
+#[test]
+fn recovers_after_errors() {
+    let (tokens, errors) = lex("let $ = 0xZZ + \"a\\q\" @ 1");
+    let tokens: Vec<Token> = tokens.into_iter().map(|token| token.value).collect();
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Let,
+            Token::Error,
+            Token::Equal,
+            Token::Error,
+            Token::Plus,
+            Token::Error,
+            Token::Error,
+            Token::Number(1.),
+        ]
+    );
+    let errors: Vec<LexError> = errors.into_iter().map(|err| err.value).collect();
+    assert_eq!(
+        errors,
+        vec![
+            LexError::BadCharacter('$'),
+            LexError::InvalidDigit {
+                digit: 'Z',
+                radix: 16
+            },
+            LexError::InvalidEscape("q".into()),
+            LexError::BadCharacter('@'),
+        ]
+    );
+}
+
+#[test]
+fn string_escapes() {
+    assert_eq!(
+        tokens(r#""a\n\t\\\"\{\u{48}""#),
+        vec![Token::String("a\n\t\\\"{H".into())]
+    );
+}
+
+#[test]
+fn invalid_escapes() {
+    assert_eq!(error(r#""\q""#), LexError::InvalidEscape("q".into()));
+    assert_eq!(error(r#""\u{zz}""#), LexError::InvalidEscape("u{".into()));
+}
+
+#[test]
+fn triple_quoted_strings() {
+    assert_eq!(
+        tokens("\"\"\"line \"one\"\nline two\"\"\""),
+        vec![Token::String("line \"one\"\nline two".into())]
+    );
+}
+
+#[test]
+fn interpolation() {
+    use compiler::lexer::tokens::Interpolation;
+    let [Token::Interpolation(parts)] = &tokens(r#""a{b + 1}c""#)[..] else {
+        panic!("expected an interpolated string");
+    };
+    let [Interpolation::String(a), Interpolation::Tokens(expr), Interpolation::String(c)] =
+        &parts[..]
+    else {
+        panic!("unexpected parts {parts:?}");
+    };
+    assert_eq!((a.as_str(), c.as_str()), ("a", "c"));
+    let expr: Vec<_> = expr.iter().map(|token| token.value.clone()).collect();
+    assert_eq!(
+        expr,
+        vec![Token::Ident("b".into()), Token::Plus, Token::Number(1.)]
+    );
+}
+
+#[test]
+fn unclosed_string_stops_at_newline() {
+    let (tokens, errors) = lex("let a = \"abc\nlet b = @");
+    let errors: Vec<_> = errors.into_iter().map(|err| err.value).collect();
+    assert_eq!(
+        errors,
+        vec![LexError::UnclosedString, LexError::BadCharacter('@')]
+    );
+    assert!(tokens
+        .iter()
+        .any(|token| token.value == Token::Ident("b".into())));
+    let (_, errors) = lex("\"a {b\nc\"");
+    assert_eq!(errors[0].value, LexError::UnclosedString);
+    assert_eq!(errors[0].pos.end.ln, 0);
+    let (_, errors) = lex("\"a\\\nb\"");
+    assert_eq!(errors[0].value, LexError::UnclosedString);
+}
+
+#[test]
+fn line_comments() {
+    assert_eq!(
+        tokens("1 // one\n2"),
+        vec![Token::Number(1.), Token::Number(2.)]
+    );
+    assert_eq!(tokens("// only a comment"), vec![]);
+    assert_eq!(tokens("//// not a doc comment\n1"), vec![Token::Number(1.)]);
+    assert_eq!(
+        tokens("/// docs\ndef"),
+        vec![Token::Doc("docs".into()), Token::Def]
+    );
+}
+
+#[test]
+fn doc_comments_only_before_def() {
+    assert_eq!(
+        tokens("/// one\n\n  /// two\n  def"),
+        vec![
+            Token::Doc("one".into()),
+            Token::Doc("two".into()),
+            Token::Def
+        ]
+    );
+    assert_eq!(tokens("/// docs\n1"), vec![Token::Number(1.)]);
+    assert_eq!(
+        tokens("/// docs\ndefault"),
+        vec![Token::Ident("default".into())]
+    );
+    assert_eq!(tokens("/// docs"), vec![]);
+    assert_eq!(
+        tokens("[1,\n  /// docs\n  2]"),
+        vec![
+            Token::BracketLeft,
+            Token::Number(1.),
+            Token::Comma,
+            Token::Number(2.),
+            Token::BracketRight
+        ]
+    );
+}
+
+#[test]
+fn nested_block_comments() {
+    assert_eq!(
+        tokens("1 /* a /* b */ still a */ 2"),
+        vec![Token::Number(1.), Token::Number(2.)]
+    );
+    assert_eq!(tokens("/* a\nb */\n1"), vec![Token::Number(1.)]);
+}
+
+#[test]
+fn unclosed_comment() {
+    assert_eq!(error("/* a"), LexError::UnclosedComment);
+    assert_eq!(error("/* a /* b */"), LexError::UnclosedComment);
+    let (_, errors) = lex("1\n  /* a");
+    assert_eq!((errors[0].pos.start.ln, errors[0].pos.start.col), (1, 2));
+}
+
+#[test]
+fn complete_brackets() {
+    assert!(complete("let a = 1"));
+    assert!(!complete("def f() {"));
+    assert!(!complete("f(1,"));
+    assert!(!complete("let a = [1,\n  [2"));
+    assert!(complete("def f() {\n  return [1, (2)]\n}"));
+    assert!(complete("}"));
+}
+
+#[test]
+fn complete_ignores_brackets_in_strings() {
+    assert!(complete("let a = \"{\""));
+    assert!(complete("let a = r\"(\""));
+    assert!(!complete("let a = \"}\" + f(\"(\""));
+}
+
+#[test]
+fn complete_strings() {
+    assert!(!complete("let a = \"\"\"one"));
+    assert!(!complete("let a = \"\"\"one\ntwo"));
+    assert!(complete("let a = \"\"\"one\ntwo\"\"\""));
+    assert!(!complete("let a = r\"one"));
+    assert!(complete("let a = r\"one\ntwo\""));
+    // an ordinary string ends at the line and is reported instead of waiting for more
+    assert!(complete("let a = \"one"));
+}
+
+#[test]
+fn complete_comments() {
+    assert!(!complete("1 /* one"));
+    assert!(!complete("1 /* one /* two */"));
+    assert!(complete("1 /* one /* two */ */"));
+    assert!(complete("1 // {"));
+}
@@ -0,0 +1,161 @@
+use compiler::{
+    lexer::{lex, position::Located, tokens::Token},
+    parser::{
+        ast::{ParseError, Statement},
+        parse,
+    },
+};
+
+fn tokens(text: &str) -> Vec<Located<Token>> {
+    let (tokens, errors) = lex(text);
+    assert!(errors.is_empty(), "lex errors: {errors:?}");
+    tokens
+}
+
+fn parse_errors(text: &str) -> (Vec<Statement>, Vec<ParseError>) {
+    let (chunk, errors) = parse(tokens(text));
+    (
+        chunk.value.0.into_iter().map(|stat| stat.value).collect(),
+        errors.into_iter().map(|err| err.value).collect(),
+    )
+}
+
+#[test]
+fn reports_every_statement_error() {
+    let (stats, errors) = parse_errors("let a = = 1\nlet b = 2\nlet = 3\nlet c = 4");
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0], ParseError::UnexpectedToken(Token::Equal));
+    assert_eq!(
+        errors[1],
+        ParseError::ExpectedToken {
+            expected: Token::Ident(Default::default()),
+            got: Token::Equal,
+        }
+    );
+    assert_eq!(stats.len(), 2);
+}
+
+#[test]
+fn recovers_inside_blocks() {
+    let (stats, errors) = parse_errors("while true {\n  let = 1\n  break\n}\nlet a = 1");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(stats.len(), 2);
+    let Statement::While { body, .. } = &stats[0] else {
+        panic!("expected a while statement, got {:?}", stats[0]);
+    };
+    assert_eq!(body.value.0.len(), 1);
+}
+
+#[test]
+fn skips_stray_closing_brace() {
+    let (stats, errors) = parse_errors("let a = 1 }\nlet b = 2");
+    assert_eq!(errors, vec![ParseError::UnexpectedToken(Token::BraceRight)]);
+    assert_eq!(stats.len(), 2);
+}
+
+#[test]
+fn unexpected_eof_points_at_end() {
+    let (_, errors) = parse(tokens("let a = [1,\n  2"));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].value, ParseError::UnexpectedEOF);
+    assert_eq!((errors[0].pos.start.ln, errors[0].pos.start.col), (1, 3));
+}
+
+#[test]
+fn required_parameter_after_default() {
+    let (_, errors) = parse_errors("def f(a = 1, b) {}");
+    assert_eq!(errors, [ParseError::RequiredAfterDefault("b".into())]);
+    let (_, errors) = parse_errors("def f(...rest, a) {}");
+    assert_eq!(
+        errors[0],
+        ParseError::ExpectedToken {
+            expected: Token::ParanRight,
+            got: Token::Comma,
+        }
+    );
+}
+
+#[test]
+fn interpolation_errors_are_reported() {
+    let (chunk, errors) = parse(tokens("let a = \"x{1 +}\""));
+    assert!(chunk.value.0.is_empty());
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].value, ParseError::UnexpectedEOF);
+    assert_eq!(errors[0].pos.start.col, 14);
+
+    let (_, errors) = parse(tokens("let a = 1\nlet b = \"{}\""));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].value, ParseError::UnexpectedEOF);
+    assert_eq!((errors[0].pos.start.ln, errors[0].pos.start.col), (1, 8));
+
+    let (_, errors) = parse_errors("\"{a b}\"");
+    assert_eq!(
+        errors,
+        [ParseError::UnexpectedToken(Token::Ident("b".into()))]
+    );
+}
+
+#[test]
+fn doc_comments_attach_to_def() {
+    let (stats, errors) = parse_errors("/// adds\n/// two numbers\ndef add(a, b) { return a + b }");
+    assert!(errors.is_empty());
+    match &stats[..] {
+        [Statement::Def { doc, .. }] => assert_eq!(doc.as_deref(), Some("adds\ntwo numbers")),
+        stats => panic!("expected a def, got {stats:?}"),
+    }
+    let (stats, _) = parse_errors("def add(a, b) { return a + b }");
+    assert!(matches!(&stats[..], [Statement::Def { doc: None, .. }]));
+}
+
+#[test]
+fn misplaced_doc_comments_are_ordinary_comments() {
+    for text in [
+        "let a = 1\n/// nothing follows",
+        "if true {\n  /// at the end of a block\n}",
+        "/// not a def\nlet a = 1",
+        "let a = [\n  1,\n  /// inside a list\n  2\n]",
+        "let a = {\n  /// inside a map\n  b = 1\n}",
+    ] {
+        let (stats, errors) = parse_errors(text);
+        assert!(errors.is_empty(), "{text:?}: {errors:?}");
+        assert!(!stats.is_empty(), "{text:?}");
+    }
+    let (stats, _) = parse_errors("/// not a def\nlet a = 1\ndef f() {}");
+    assert!(matches!(&stats[1], Statement::Def { doc: None, .. }));
+}
+
+#[test]
+fn newline_ends_statement_before_paren_bracket_and_minus() {
+    for text in ["let a = f\n(b)(1)", "let a = 1\n[a, a]", "let a = 2\n-1"] {
+        let (stats, errors) = parse_errors(text);
+        assert!(errors.is_empty(), "{text:?}: {errors:?}");
+        assert_eq!(stats.len(), 2, "{text:?}");
+    }
+    for text in ["let a = m\n.b", "let a = 2\n+ 1", "let a = f(\n1\n)"] {
+        let (stats, errors) = parse_errors(text);
+        assert!(errors.is_empty(), "{text:?}: {errors:?}");
+        assert_eq!(stats.len(), 1, "{text:?}");
+    }
+}
+
+#[test]
+fn expression_spans_cover_operands() {
+    let span = |text: &str| {
+        let (chunk, errors) = parse(tokens(text));
+        assert!(errors.is_empty(), "{errors:?}");
+        match &chunk.value.0[..] {
+            [Located {
+                value: Statement::Expression(expr),
+                pos: _,
+            }] => (
+                (expr.pos.start.ln, expr.pos.start.col),
+                (expr.pos.end.ln, expr.pos.end.col),
+            ),
+            stats => panic!("expected one expression, got {stats:?}"),
+        }
+    };
+    assert_eq!(span("  a + b * c"), ((0, 2), (0, 11)));
+    assert_eq!(span("f(1,\n  g(2))"), ((0, 0), (1, 7)));
+    assert_eq!(span("(a + b) - c"), ((0, 0), (0, 11)));
+    assert_eq!(span("a.b[1](2)"), ((0, 0), (0, 9)));
+}
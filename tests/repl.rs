@@ -0,0 +1,68 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// runs the REPL on `input` and returns what it wrote to stdout and stderr
+fn repl(input: &str) -> (String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_compiler"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to start the REPL");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn help() {
+    let (out, err) = repl(":help\n");
+    assert!(out.contains(":quit             leave the REPL"), "{out}");
+    assert!(err.is_empty(), "{err}");
+}
+
+#[test]
+fn quit_stops_reading() {
+    let (out, _) = repl("1\n:quit\n2\n");
+    assert_eq!(out, "> 1.0\n> \n");
+    let (out, _) = repl(":q\n2\n");
+    assert_eq!(out, "> \n");
+}
+
+#[test]
+fn unknown_command() {
+    let (out, err) = repl(":foo bar\n1\n");
+    assert_eq!(err, "unknown command :foo, try :help\n");
+    assert_eq!(out, "> > 1.0\n> \n");
+}
+
+#[test]
+fn globals() {
+    let (out, _) = repl("let a = 1\n:globals\n");
+    assert!(out.contains("a = 1.0\n"), "{out}");
+}
+
+#[test]
+fn continues_unfinished_input() {
+    let (out, err) = repl("def f() {\n  return 2\n}\nf()\n");
+    assert!(err.is_empty(), "{err}");
+    assert_eq!(out, "> . . > 2.0\n> \n");
+}
+
+#[test]
+fn ast_and_bytecode() {
+    let (out, _) = repl(":ast 1\n:bytecode 1\n");
+    assert!(out.contains("Number("), "{out}");
+    assert!(out.contains("closure <main>"), "{out}");
+}
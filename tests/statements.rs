@@ -0,0 +1,134 @@
+mod common;
+use common::eval;
+use compiler::interpreter::value::Value;
+
+#[test]
+fn last_expression_is_result() {
+    assert_eq!(eval("1 + 2"), Value::Number(3.));
+    assert_eq!(eval("let a = 2\nlet b = 3\na * b"), Value::Number(6.));
+}
+
+#[test]
+fn no_trailing_expression() {
+    assert_eq!(eval("let a = 1"), Value::Null);
+}
+
+#[test]
+fn call_parenthesized_head() {
+    assert_eq!(eval("def f(x) { return x + 1 }\n(f)(1)"), Value::Number(2.));
+}
+
+#[test]
+fn paren_on_next_line_starts_statement() {
+    assert_eq!(
+        eval("def f(x) { return x + 1 }\nlet a = f\n(f)(1)"),
+        Value::Number(2.)
+    );
+    assert_eq!(
+        eval("def f(x) { return x + 1 }\nf(\n  1\n)"),
+        Value::Number(2.)
+    );
+}
+
+#[test]
+fn bracket_on_next_line_starts_statement() {
+    assert_eq!(format!("{:?}", eval("let a = 1\n[a, a]")), "[1.0, 1.0]");
+    assert_eq!(eval("let a = [1, 2]\na[1]"), Value::Number(2.));
+}
+
+#[test]
+fn dot_on_next_line_continues_expression() {
+    assert_eq!(
+        eval("let m = { a = { b = 3 } }\nm\n  .a\n  .b"),
+        Value::Number(3.)
+    );
+}
+
+#[test]
+fn minus_on_next_line_starts_statement() {
+    assert_eq!(eval("let a = 2\n-1"), Value::Number(-1.));
+    assert_eq!(eval("let a = 2\n-1\na"), Value::Number(2.));
+    assert_eq!(eval("let a = 2 -\n1\na"), Value::Number(1.));
+}
+
+#[test]
+fn operator_on_next_line_continues_expression() {
+    assert_eq!(eval("let a = 2\n  + 1\na"), Value::Number(3.));
+    assert_eq!(eval("let a = false\n  | true\na"), Value::Boolean(true));
+}
+
+#[test]
+fn chained_calls() {
+    assert_eq!(
+        eval("def g(a) {\n  def h(b) { return a * b }\n  return h\n}\ng(3)(4)"),
+        Value::Number(12.)
+    );
+}
+
+#[test]
+fn expression_statements_are_evaluated() {
+    assert_eq!(
+        eval("let l = []\npush(l, 1)\nlen([1, 2])\npush(l, 2)\nlen(l)"),
+        Value::Number(2.)
+    );
+}
+
+#[test]
+fn self_referential_values_print() {
+    let value = eval("let l = [1]\npush(l, l)\nlet m = {}\nm.self = m\npush(l, m)\nresult = l");
+    assert_eq!(format!("{value:?}"), "[1.0, [...], {self = {...}}]");
+    assert_eq!(value.to_string(), "[1.0, [...], {self = {...}}]");
+}
+
+#[test]
+fn interpolation() {
+    let value = eval("let n = 2\nresult = \"{n} + {n} = {n + n}, {[1]} {null}\"");
+    assert_eq!(value, Value::from("2 + 2 = 4, [1.0] null"));
+}
+
+#[test]
+fn interpolation_ignores_tostring_variables() {
+    let value = eval("def f(tostring) { return \"<{1}>\" }\nresult = f(null)");
+    assert_eq!(value, Value::from("<1>"));
+}
+
+#[test]
+fn for_in_list() {
+    let value = eval("let sum = 0\nfor n in [1, 2, 3] { sum = sum + n }\nsum");
+    assert_eq!(value, Value::Number(6.));
+}
+
+#[test]
+fn for_in_string() {
+    let value = eval("let chars = []\nfor c in \"abc\" { push(chars, c) }\nchars");
+    assert_eq!(format!("{value:?}"), r#"["a", "b", "c"]"#);
+}
+
+#[test]
+fn for_in_map() {
+    let value = eval("let keys = []\nfor key in { a = 1, b = 2 } { push(keys, key) }\nkeys");
+    let Value::List(keys) = value else {
+        panic!("expected a list, got {value:?}");
+    };
+    let mut keys: Vec<String> = keys.borrow().iter().map(|key| key.to_string()).collect();
+    keys.sort();
+    assert_eq!(keys, ["a", "b"]);
+}
+
+#[test]
+fn range() {
+    let collect = |args: &str| {
+        let value = eval(&format!(
+            "let items = []\nfor i in range({args}) {{ push(items, i) }}\nitems"
+        ));
+        format!("{value:?}")
+    };
+    assert_eq!(collect("3"), "[0.0, 1.0, 2.0]");
+    assert_eq!(collect("2, 5"), "[2.0, 3.0, 4.0]");
+    assert_eq!(collect("0, 10, 4"), "[0.0, 4.0, 8.0]");
+    assert_eq!(collect("3, 0, -1"), "[3.0, 2.0, 1.0]");
+    assert_eq!(collect("5, 0, -2"), "[5.0, 3.0, 1.0]");
+    assert_eq!(collect("3, 3"), "[]");
+    assert_eq!(collect("5, 2"), "[]");
+    assert_eq!(collect("0, 3, -1"), "[]");
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use common::eval;
+use compiler::interpreter::value::{Function, Value};
+use std::{collections::HashMap, rc::Rc};
+
+fn truthy(value: Value) -> bool {
+    bool::from(&value)
+}
+
+#[test]
+fn null() {
+    assert!(!truthy(Value::Null));
+}
+
+#[test]
+fn boolean() {
+    assert!(truthy(Value::Boolean(true)));
+    assert!(!truthy(Value::Boolean(false)));
+}
+
+#[test]
+fn number() {
+    assert!(truthy(Value::Number(1.)));
+    assert!(truthy(Value::Number(-1.)));
+    assert!(truthy(Value::Number(0.5)));
+    assert!(truthy(Value::Number(f64::INFINITY)));
+    assert!(!truthy(Value::Number(0.)));
+    assert!(!truthy(Value::Number(-0.)));
+    assert!(!truthy(Value::Number(f64::NAN)));
+}
+
+#[test]
+fn string() {
+    assert!(truthy(Value::from("a")));
+    assert!(truthy(Value::from("0")));
+    assert!(truthy(Value::from("false")));
+    assert!(!truthy(Value::from("")));
+}
+
+#[test]
+fn list() {
+    assert!(truthy(Value::from(vec![Value::Null])));
+    assert!(!truthy(Value::from(vec![])));
+}
+
+#[test]
+fn map() {
+    assert!(truthy(Value::from(HashMap::from([(
+        "a".to_string(),
+        Value::Null
+    )]))));
+    assert!(!truthy(Value::from(HashMap::new())));
+}
+
+#[test]
+fn function() {
+    fn native(
+        _: &mut compiler::interpreter::interpreter::Interpreter,
+        _: Vec<Value>,
+    ) -> Result<Value, compiler::interpreter::interpreter::NativeError> {
+        Ok(Value::Null)
+    }
+    assert!(truthy(Value::Function(Rc::new(Function::NativeFunction(
+        Rc::new(native)
+    )))));
+    assert!(truthy(eval("def f() {} result = f")));
+}
+
+#[test]
+fn iterator() {
+    assert!(truthy(eval("result = range(0)")));
+}
+
+#[test]
+fn jump_if() {
+    let value = eval(
+        r#"result = []
+        if 1 { push(result, "a") }
+        if 0 { push(result, "b") }
+        if "" { push(result, "c") } else { push(result, "d") }
+        if [] { push(result, "e") }
+        if [0] { push(result, "f") }
+        if {} { push(result, "g") }
+        if null { push(result, "h") }"#,
+    );
+    assert_eq!(format!("{value:?}"), r#"["a", "d", "f"]"#);
+}
+
+#[test]
+fn while_number() {
+    let value = eval(
+        "let n = 5
+        result = 0
+        while n {
+            n = n - 1
+            result = result + 1
+        }",
+    );
+    assert_eq!(value, Value::Number(5.));
+}
+
+#[test]
+fn not() {
+    let value = eval(r#"result = [!0, !1, !"", !"a", ![], !null, !true]"#);
+    assert_eq!(
+        format!("{value:?}"),
+        "[true, false, true, false, true, true, false]"
+    );
+}
+
+#[test]
+fn and_or() {
+    let value = eval(r#"result = [0 & 1, 2 & 3, 0 | 4, 5 | 6, "" | "x", null & 7]"#);
+    assert_eq!(format!("{value:?}"), "[0.0, 3.0, 4.0, 5.0, \"x\", null]");
+}
+
+#[test]
+fn and_or_short_circuit() {
+    let value = eval(
+        "let calls = 0
+def f() {
+    calls = calls + 1
+    return true
+}
+let a = false & f()
+let b = true | f()
+let c = true & f()
+let d = false | f()
+result = [a, b, c, d, calls]",
+    );
+    assert_eq!(format!("{value:?}"), "[false, true, true, true, 2.0]");
+}